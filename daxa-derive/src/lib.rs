@@ -0,0 +1,103 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitStr};
+
+/// Implements `daxa_rs::GpuType` for a `#[repr(C)]` struct and checks at compile time that its
+/// layout matches the shader side, see the `daxa_rs::GpuStruct` documentation.
+#[proc_macro_derive(GpuStruct, attributes(gpu))]
+pub fn derive_gpu_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    gpu_struct(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn gpu_struct(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "GpuStruct can not be derived for generic structs"));
+    }
+
+    let mut repr_c = false;
+    let mut std430 = false;
+    for attr in &input.attrs {
+        if attr.path().is_ident("repr") {
+            attr.parse_nested_meta(|meta| {
+                repr_c |= meta.path.is_ident("C");
+                // Skips the arguments of `align(N)` and `packed(N)`.
+                if meta.input.peek(syn::token::Paren) {
+                    let _arguments;
+                    syn::parenthesized!(_arguments in meta.input);
+                }
+                Ok(())
+            })?;
+        } else if attr.path().is_ident("gpu") {
+            attr.parse_nested_meta(|meta| {
+                if !meta.path.is_ident("layout") {
+                    return Err(meta.error("expected `layout = \"scalar\"` or `layout = \"std430\"`"));
+                }
+                let layout: LitStr = meta.value()?.parse()?;
+                std430 = match layout.value().as_str() {
+                    "scalar" => false,
+                    "std430" => true,
+                    _ => return Err(Error::new_spanned(layout, "expected \"scalar\" or \"std430\"")),
+                };
+                Ok(())
+            })?;
+        }
+    }
+    if !repr_c {
+        return Err(Error::new_spanned(name, "GpuStruct needs #[repr(C)] for a stable field order"));
+    }
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(name, "GpuStruct can only be derived for structs"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(&data.fields, "GpuStruct needs named fields"));
+    };
+    let idents: Vec<_> = fields.named.iter().map(|field| field.ident.as_ref().unwrap()).collect();
+    let field_names: Vec<_> = idents.iter().map(|ident| ident.to_string()).collect();
+    let types: Vec<_> = fields.named.iter().map(|field| &field.ty).collect();
+    let indices = 0..idents.len();
+
+    let name_string = name.to_string();
+    let layout = if std430 { "std430" } else { "scalar" };
+    let offset_messages = field_names.iter().map(|field| {
+        format!("field `{}` of `{}` is not at its {} offset, add padding before it", field, name, layout)
+    });
+    let size_message = format!("the size of `{}` does not match its {} size, add padding at the end", name, layout);
+
+    let scalar_members = quote! {
+        [#((<#types as ::daxa_rs::GpuType>::SCALAR_ALIGN, <#types as ::daxa_rs::GpuType>::SCALAR_SIZE)),*]
+    };
+    let std430_members = quote! {
+        [#((<#types as ::daxa_rs::GpuType>::STD430_ALIGN, <#types as ::daxa_rs::GpuType>::STD430_SIZE)),*]
+    };
+    let checked_members = if std430 { &std430_members } else { &scalar_members };
+
+    Ok(quote! {
+        unsafe impl ::daxa_rs::GpuType for #name {
+            const SCALAR_ALIGN: usize = ::daxa_rs::gpu_struct_layout(#scalar_members).1;
+            const SCALAR_SIZE: usize = ::daxa_rs::gpu_struct_layout(#scalar_members).2;
+            const STD430_ALIGN: usize = ::daxa_rs::gpu_struct_layout(#std430_members).1;
+            const STD430_SIZE: usize = ::daxa_rs::gpu_struct_layout(#std430_members).2;
+
+            fn glsl_name() -> ::std::string::String {
+                ::std::borrow::ToOwned::to_owned(#name_string)
+            }
+
+            fn glsl_declarations(header: &mut ::daxa_rs::GlslHeader) {
+                #(<#types as ::daxa_rs::GpuType>::glsl_declarations(header);)*
+                let mut declaration = ::std::format!("struct {}\n{{\n", #name_string);
+                #(declaration += &::std::format!("    {};\n", <#types as ::daxa_rs::GpuType>::glsl_declarator(#field_names));)*
+                declaration += &::std::format!("}};\nDAXA_DECL_BUFFER_PTR({})\n", #name_string);
+                header.add_declaration(#name_string, declaration);
+            }
+        }
+
+        const _: () = {
+            let (offsets, _, size) = ::daxa_rs::gpu_struct_layout(#checked_members);
+            #(::core::assert!(offsets[#indices] == ::core::mem::offset_of!(#name, #idents), #offset_messages);)*
+            ::core::assert!(size == ::core::mem::size_of::<#name>(), #size_message);
+        };
+    })
+}
@@ -0,0 +1,9 @@
+// Everything the wrappers call goes through this module, imported as `daxa_sys` in every file.
+// Types and constants always come from the real bindings, the functions are replaced by the
+// null backend when the `null-backend` feature is enabled and in the crate's own tests.
+
+#[cfg(not(any(test, feature = "null-backend")))]
+pub(crate) use daxa_sys::*;
+
+#[cfg(any(test, feature = "null-backend"))]
+pub(crate) use crate::null_backend::*;
@@ -0,0 +1,726 @@
+use std::fmt::Write as _;
+use std::mem;
+use std::path::Path;
+
+use crate::backend as daxa_sys;
+use crate::command_recorder::*;
+use crate::types::*;
+
+const MAGIC: &[u8; 8] = b"DAXACAP\0";
+const VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Io(std::io::Error),
+    /// The data does not start with the capture file magic.
+    NotACapture,
+    UnsupportedVersion(u32),
+    /// The data ends in the middle of a command.
+    Truncated,
+    InvalidString,
+    UnknownValueTag(u8),
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureError::Io(error) => write!(f, "could not read or write the capture: {}", error),
+            CaptureError::NotACapture => write!(f, "the data is not a command capture"),
+            CaptureError::UnsupportedVersion(version) => write!(f, "unsupported capture version {}", version),
+            CaptureError::Truncated => write!(f, "the capture ends in the middle of a command"),
+            CaptureError::InvalidString => write!(f, "the capture contains a string that is not utf-8"),
+            CaptureError::UnknownValueTag(tag) => write!(f, "the capture contains an unknown value tag {}", tag),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {
+    fn source(&self) -> std::option::Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CaptureError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CaptureError {
+    fn from(error: std::io::Error) -> Self {
+        CaptureError::Io(error)
+    }
+}
+
+/// Every command recorded while capturing, with its parameters and the ids and names of the
+/// resources it used, see [`CommandRecorder::begin_capture`].
+///
+/// The binary form is meant to be attached to bug reports, [`CommandCapture::dump`] turns it
+/// into text that can be read and diffed between frames. Events, query pools and pipelines have
+/// no ids, they are numbered in the order they first appear in the capture instead, so a dump does
+/// not depend on what else was created before.
+#[derive(Clone, Default)]
+pub struct CommandCapture {
+    // Encoded commands, without the file header.
+    commands: Vec<u8>,
+    command_count: usize,
+    // Value tag and serial of every handle seen so far, see `CommandEncoder::handle`.
+    handles: Vec<(u8, u64)>,
+}
+
+impl CommandCapture {
+    pub fn command_count(&self) -> usize {
+        self.command_count
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 4 + self.commands.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.commands);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> std::result::Result<Self, CaptureError> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(CaptureError::NotACapture);
+        }
+        let mut reader = Reader {
+            bytes,
+            position: MAGIC.len(),
+        };
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(CaptureError::UnsupportedVersion(version));
+        }
+        let commands = &bytes[reader.position..];
+        // Decode everything once, so a broken capture is reported here and not while dumping.
+        let command_count = decode(commands)?.len();
+        Ok(Self {
+            commands: commands.to_vec(),
+            command_count,
+            handles: Vec::new(),
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::result::Result<(), CaptureError> {
+        Ok(std::fs::write(path, self.to_bytes())?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> std::result::Result<Self, CaptureError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// One line per command, followed by one indented line per parameter.
+    pub fn dump(&self) -> String {
+        let mut text = String::new();
+        // Captures are validated on construction.
+        for (index, command) in decode(&self.commands).unwrap().iter().enumerate() {
+            let _ = writeln!(text, "#{} {}", index, command.name);
+            for (name, value) in &command.fields {
+                let _ = writeln!(text, "    {}: {}", name, value);
+            }
+        }
+        text
+    }
+
+    pub(crate) fn command(&mut self, device: daxa_sys::daxa_Device, name: &str) -> CommandEncoder<'_> {
+        self.command_count += 1;
+        write_str(&mut self.commands, name);
+        let field_count_position = self.commands.len();
+        self.commands.extend_from_slice(&0u32.to_le_bytes());
+        CommandEncoder {
+            bytes: &mut self.commands,
+            handles: &mut self.handles,
+            device,
+            field_count_position,
+            field_count: 0,
+        }
+    }
+}
+
+/// Pretty prints a capture file written by [`CommandCapture::save`].
+pub fn dump_capture_file(path: impl AsRef<Path>) -> std::result::Result<String, CaptureError> {
+    Ok(CommandCapture::load(path)?.dump())
+}
+
+const TAG_U64: u8 = 0;
+const TAG_F32: u8 = 1;
+const TAG_STR: u8 = 2;
+const TAG_BYTES: u8 = 3;
+const TAG_STAGES: u8 = 4;
+const TAG_ACCESS: u8 = 5;
+const TAG_SLICE: u8 = 6;
+const TAG_BUFFER: u8 = 7;
+const TAG_IMAGE: u8 = 8;
+const TAG_IMAGE_VIEW: u8 = 9;
+const TAG_SAMPLER: u8 = 10;
+const TAG_EVENT: u8 = 11;
+const TAG_TIMELINE_QUERY_POOL: u8 = 12;
+const TAG_COMPUTE_PIPELINE: u8 = 13;
+const TAG_OFFSET: u8 = 14;
+const TAG_EXTENT: u8 = 15;
+
+/// Appends the parameters of one command to a capture.
+pub(crate) struct CommandEncoder<'a> {
+    bytes: &'a mut Vec<u8>,
+    handles: &'a mut Vec<(u8, u64)>,
+    device: daxa_sys::daxa_Device,
+    field_count_position: usize,
+    field_count: u32,
+}
+
+macro_rules! encode_resource_fn {
+    ($name:ident, $type:ident, $tag:expr) => {
+        paste::item! {
+            /// Stores the id together with the resource's debug name at the time of recording.
+            pub(crate) fn $name(&mut self, field: &str, id: [< $type Id >]) {
+                let name = unsafe {
+                    let mut info = mem::zeroed::<daxa_sys:: [< daxa_ $type Info >]>();
+                    match mem::transmute::<daxa_sys::daxa_Result, Result>(daxa_sys:: [< daxa_dvc_info_ $name >] (self.device, id, &mut info)) {
+                        Result::Success => mem::transmute::<_, [< $type Info >]>(info).name.as_str().to_owned(),
+                        _ => String::new(),
+                    }
+                };
+                self.field(field, $tag);
+                self.bytes.extend_from_slice(&id.value.to_le_bytes());
+                write_str(self.bytes, &name);
+            }
+        }
+    };
+}
+
+impl<'a> CommandEncoder<'a> {
+    fn field(&mut self, name: &str, tag: u8) {
+        self.field_count += 1;
+        write_str(self.bytes, name);
+        self.bytes.push(tag);
+    }
+
+    pub(crate) fn u64(&mut self, name: &str, value: u64) {
+        self.field(name, TAG_U64);
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn f32(&mut self, name: &str, value: f32) {
+        self.field(name, TAG_F32);
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn str(&mut self, name: &str, value: &str) {
+        self.field(name, TAG_STR);
+        write_str(self.bytes, value);
+    }
+
+    pub(crate) fn bytes(&mut self, name: &str, value: &[u8]) {
+        self.field(name, TAG_BYTES);
+        self.bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        self.bytes.extend_from_slice(value);
+    }
+
+    /// Image layouts of C info structs, which hold them as plain integers.
+    pub(crate) fn layout(&mut self, name: &str, value: daxa_sys::daxa_ImageLayout) {
+        let layout = [
+            ImageLayout::Undefined,
+            ImageLayout::General,
+            ImageLayout::TransferSrcOptimal,
+            ImageLayout::TransferDstOptimal,
+            ImageLayout::ReadOnlyOptimal,
+            ImageLayout::AttachmentOptimal,
+            ImageLayout::PresentSrc,
+        ]
+        .into_iter()
+        .find(|layout| *layout as daxa_sys::daxa_ImageLayout == value);
+        match layout {
+            Some(layout) => self.str(name, &format!("{:?}", layout)),
+            None => self.u64(name, value as u64),
+        }
+    }
+
+    pub(crate) fn stages(&mut self, name: &str, value: PipelineStageFlags) {
+        self.field(name, TAG_STAGES);
+        self.bytes.extend_from_slice(&value.bits().to_le_bytes());
+    }
+
+    pub(crate) fn access(&mut self, name: &str, value: Access) {
+        self.field(name, TAG_ACCESS);
+        self.bytes.extend_from_slice(&value.stages.bits().to_le_bytes());
+        self.bytes.extend_from_slice(&value.ty.bits().to_le_bytes());
+    }
+
+    pub(crate) fn slice(&mut self, name: &str, value: ImageMipArraySlice) {
+        self.field(name, TAG_SLICE);
+        for part in [value.base_mip_level, value.level_count, value.base_array_layer, value.layer_count] {
+            self.bytes.extend_from_slice(&part.to_le_bytes());
+        }
+    }
+
+    /// A single mip of an image as used by copies and blits.
+    pub(crate) fn array_slice(&mut self, name: &str, value: daxa_sys::daxa_ImageArraySlice) {
+        self.slice(
+            name,
+            ImageMipArraySlice {
+                base_mip_level: value.mip_level,
+                level_count: 1,
+                base_array_layer: value.base_array_layer,
+                layer_count: value.layer_count,
+            },
+        );
+    }
+
+    pub(crate) fn offset(&mut self, name: &str, value: daxa_sys::VkOffset3D) {
+        self.field(name, TAG_OFFSET);
+        for part in [value.x, value.y, value.z] {
+            self.bytes.extend_from_slice(&part.to_le_bytes());
+        }
+    }
+
+    pub(crate) fn extent(&mut self, name: &str, value: daxa_sys::VkExtent3D) {
+        self.field(name, TAG_EXTENT);
+        for part in [value.width, value.height, value.depth] {
+            self.bytes.extend_from_slice(&part.to_le_bytes());
+        }
+    }
+
+    // Stores the handle's number in order of first appearance. Objects are told apart by their
+    // serial, the address of a destroyed object can be reused by the next one.
+    fn handle(&mut self, name: &str, tag: u8, serial: u64) {
+        let mut known = self.handles.iter().filter(|(known_tag, _)| *known_tag == tag).map(|&(_, known)| known);
+        let position = known.position(|known| known == serial);
+        let index = position.unwrap_or_else(|| {
+            let index = self.handles.iter().filter(|(known_tag, _)| *known_tag == tag).count();
+            self.handles.push((tag, serial));
+            index
+        });
+        self.field(name, tag);
+        self.bytes.extend_from_slice(&(index as u64).to_le_bytes());
+    }
+
+    pub(crate) fn event(&mut self, name: &str, serial: u64) {
+        self.handle(name, TAG_EVENT, serial);
+    }
+
+    pub(crate) fn timeline_query_pool(&mut self, name: &str, serial: u64) {
+        self.handle(name, TAG_TIMELINE_QUERY_POOL, serial);
+    }
+
+    pub(crate) fn compute_pipeline(&mut self, name: &str, serial: u64) {
+        self.handle(name, TAG_COMPUTE_PIPELINE, serial);
+    }
+
+    encode_resource_fn!(buffer, Buffer, TAG_BUFFER);
+    encode_resource_fn!(image, Image, TAG_IMAGE);
+    encode_resource_fn!(image_view, ImageView, TAG_IMAGE_VIEW);
+    encode_resource_fn!(sampler, Sampler, TAG_SAMPLER);
+}
+
+impl<'a> Drop for CommandEncoder<'a> {
+    fn drop(&mut self) {
+        let position = self.field_count_position;
+        self.bytes[position..position + 4].copy_from_slice(&self.field_count.to_le_bytes());
+    }
+}
+
+/// Info structs passed to the recorder functions that are generated by macros.
+pub(crate) trait CaptureInfo {
+    fn capture(&self, encoder: &mut CommandEncoder);
+}
+
+impl CaptureInfo for BufferCopyInfo {
+    fn capture(&self, encoder: &mut CommandEncoder) {
+        encoder.buffer("src_buffer", self.src_buffer);
+        encoder.buffer("dst_buffer", self.dst_buffer);
+        encoder.u64("src_offset", self.src_offset as u64);
+        encoder.u64("dst_offset", self.dst_offset as u64);
+        encoder.u64("size", self.size as u64);
+    }
+}
+
+impl CaptureInfo for BufferImageCopyInfo {
+    fn capture(&self, encoder: &mut CommandEncoder) {
+        encoder.buffer("buffer", self.buffer);
+        encoder.u64("buffer_offset", self.buffer_offset as u64);
+        encoder.image("image", self.image);
+        encoder.layout("image_layout", self.image_layout);
+        encoder.array_slice("image_slice", self.image_slice);
+        encoder.offset("image_offset", self.image_offset);
+        encoder.extent("image_extent", self.image_extent);
+    }
+}
+
+impl CaptureInfo for ImageBufferCopyInfo {
+    fn capture(&self, encoder: &mut CommandEncoder) {
+        encoder.image("image", self.image);
+        encoder.layout("image_layout", self.image_layout);
+        encoder.array_slice("image_slice", self.image_slice);
+        encoder.offset("image_offset", self.image_offset);
+        encoder.extent("image_extent", self.image_extent);
+        encoder.buffer("buffer", self.buffer);
+        encoder.u64("buffer_offset", self.buffer_offset as u64);
+    }
+}
+
+impl CaptureInfo for ImageCopyInfo {
+    fn capture(&self, encoder: &mut CommandEncoder) {
+        encoder.image("src_image", self.src_image);
+        encoder.layout("src_image_layout", self.src_image_layout);
+        encoder.image("dst_image", self.dst_image);
+        encoder.layout("dst_image_layout", self.dst_image_layout);
+        encoder.array_slice("src_slice", self.src_slice);
+        encoder.offset("src_offset", self.src_offset);
+        encoder.array_slice("dst_slice", self.dst_slice);
+        encoder.offset("dst_offset", self.dst_offset);
+        encoder.extent("extent", self.extent);
+    }
+}
+
+impl CaptureInfo for ImageBlitInfo {
+    fn capture(&self, encoder: &mut CommandEncoder) {
+        encoder.image("src_image", self.src_image);
+        encoder.layout("src_image_layout", self.src_image_layout);
+        encoder.image("dst_image", self.dst_image);
+        encoder.layout("dst_image_layout", self.dst_image_layout);
+        encoder.array_slice("src_slice", self.src_slice);
+        encoder.offset("src_offsets[0]", self.src_offsets[0]);
+        encoder.offset("src_offsets[1]", self.src_offsets[1]);
+        encoder.array_slice("dst_slice", self.dst_slice);
+        encoder.offset("dst_offsets[0]", self.dst_offsets[0]);
+        encoder.offset("dst_offsets[1]", self.dst_offsets[1]);
+        // NEAREST is 0, which bitflags prints as empty.
+        let filter = Filter::from_bits_truncate(self.filter);
+        if filter.is_empty() {
+            encoder.str("filter", "NEAREST");
+        } else {
+            encoder.str("filter", &format!("{:?}", filter));
+        }
+    }
+}
+
+impl CaptureInfo for BufferClearInfo {
+    fn capture(&self, encoder: &mut CommandEncoder) {
+        encoder.buffer("buffer", self.buffer);
+        encoder.u64("offset", self.offset as u64);
+        encoder.u64("size", self.size as u64);
+        encoder.u64("clear_value", self.clear_value as u64);
+    }
+}
+
+impl CaptureInfo for SetUniformBufferInfo {
+    fn capture(&self, encoder: &mut CommandEncoder) {
+        encoder.u64("slot", self.slot as u64);
+        encoder.buffer("buffer", self.buffer);
+        encoder.u64("size", self.size as u64);
+        encoder.u64("offset", self.offset as u64);
+    }
+}
+
+impl CaptureInfo for DispatchInfo {
+    fn capture(&self, encoder: &mut CommandEncoder) {
+        encoder.u64("x", self.x as u64);
+        encoder.u64("y", self.y as u64);
+        encoder.u64("z", self.z as u64);
+    }
+}
+
+impl CaptureInfo for MemoryBarrierInfo {
+    fn capture(&self, encoder: &mut CommandEncoder) {
+        encoder.access("src_access", self.src_access);
+        encoder.access("dst_access", self.dst_access);
+    }
+}
+
+impl CaptureInfo for ImageMemoryBarrierInfo {
+    fn capture(&self, encoder: &mut CommandEncoder) {
+        encoder.access("src_access", self.src_access);
+        encoder.access("dst_access", self.dst_access);
+        encoder.str("src_layout", &format!("{:?}", self.src_layout));
+        encoder.str("dst_layout", &format!("{:?}", self.dst_layout));
+        encoder.slice("image_slice", self.image_slice);
+        encoder.image("image", self.image_id);
+    }
+}
+
+fn write_str(bytes: &mut Vec<u8>, string: &str) {
+    // Lengths are stored in 16 bits, longer strings are cut at the last char boundary that fits.
+    let mut len = string.len().min(u16::MAX as usize);
+    while !string.is_char_boundary(len) {
+        len -= 1;
+    }
+    bytes.extend_from_slice(&(len as u16).to_le_bytes());
+    bytes.extend_from_slice(&string.as_bytes()[..len]);
+}
+
+struct DecodedCommand {
+    name: String,
+    fields: Vec<(String, Value)>,
+}
+
+enum Value {
+    U64(u64),
+    F32(f32),
+    Str(String),
+    Bytes(Vec<u8>),
+    Stages(u64),
+    Access(u64, u64),
+    Slice([u32; 4]),
+    Resource(&'static str, u64, String),
+    Handle(&'static str, u64),
+    Offset([i32; 3]),
+    Extent([u32; 3]),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::U64(value) => write!(f, "{}", value),
+            Value::F32(value) => write!(f, "{}", value),
+            Value::Str(value) => write!(f, "{:?}", value),
+            Value::Bytes(bytes) => {
+                write!(f, "{} bytes [", bytes.len())?;
+                for (index, byte) in bytes.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { " " };
+                    write!(f, "{}{:02x}", separator, byte)?;
+                }
+                write!(f, "]")
+            }
+            Value::Stages(stages) => write!(f, "{:?}", PipelineStageFlags::from_bits_truncate(*stages)),
+            Value::Access(stages, ty) => write!(
+                f,
+                "{:?} / {:?}",
+                PipelineStageFlags::from_bits_truncate(*stages),
+                AccessTypeFlags::from_bits_truncate(*ty)
+            ),
+            Value::Slice([base_mip_level, level_count, base_array_layer, layer_count]) => write!(
+                f,
+                "mips {}..{}, layers {}..{}",
+                base_mip_level,
+                base_mip_level + level_count,
+                base_array_layer,
+                base_array_layer + layer_count
+            ),
+            Value::Resource(kind, id, name) if name.is_empty() => write!(f, "{} {:#x}", kind, id),
+            Value::Resource(kind, id, name) => write!(f, "{} {:#x} {:?}", kind, id, name),
+            Value::Handle(kind, index) => write!(f, "{} #{}", kind, index),
+            Value::Offset([x, y, z]) => write!(f, "({}, {}, {})", x, y, z),
+            Value::Extent([width, height, depth]) => write!(f, "{}x{}x{}", width, height, depth),
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> std::result::Result<&'a [u8], CaptureError> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + count)
+            .ok_or(CaptureError::Truncated)?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> std::result::Result<[u8; N], CaptureError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> std::result::Result<u8, CaptureError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> std::result::Result<u16, CaptureError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> std::result::Result<u32, CaptureError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> std::result::Result<u64, CaptureError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn string(&mut self) -> std::result::Result<String, CaptureError> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| CaptureError::InvalidString)
+    }
+
+    fn value(&mut self) -> std::result::Result<Value, CaptureError> {
+        Ok(match self.u8()? {
+            TAG_U64 => Value::U64(self.u64()?),
+            TAG_F32 => Value::F32(f32::from_le_bytes(self.array()?)),
+            TAG_STR => Value::Str(self.string()?),
+            TAG_BYTES => {
+                let len = self.u32()? as usize;
+                Value::Bytes(self.take(len)?.to_vec())
+            }
+            TAG_STAGES => Value::Stages(self.u64()?),
+            TAG_ACCESS => Value::Access(self.u64()?, self.u64()?),
+            TAG_SLICE => Value::Slice([self.u32()?, self.u32()?, self.u32()?, self.u32()?]),
+            tag @ TAG_BUFFER..=TAG_SAMPLER => {
+                let kind = ["buffer", "image", "image view", "sampler"][(tag - TAG_BUFFER) as usize];
+                Value::Resource(kind, self.u64()?, self.string()?)
+            }
+            tag @ TAG_EVENT..=TAG_COMPUTE_PIPELINE => {
+                let kind = ["event", "timeline query pool", "compute pipeline"][(tag - TAG_EVENT) as usize];
+                Value::Handle(kind, self.u64()?)
+            }
+            TAG_OFFSET => Value::Offset([
+                i32::from_le_bytes(self.array()?),
+                i32::from_le_bytes(self.array()?),
+                i32::from_le_bytes(self.array()?),
+            ]),
+            TAG_EXTENT => Value::Extent([self.u32()?, self.u32()?, self.u32()?]),
+            tag => return Err(CaptureError::UnknownValueTag(tag)),
+        })
+    }
+}
+
+fn decode(commands: &[u8]) -> std::result::Result<Vec<DecodedCommand>, CaptureError> {
+    let mut reader = Reader {
+        bytes: commands,
+        position: 0,
+    };
+    let mut decoded = Vec::new();
+    while reader.position < commands.len() {
+        let name = reader.string()?;
+        let field_count = reader.u32()?;
+        let fields = (0..field_count)
+            .map(|_| Ok((reader.string()?, reader.value()?)))
+            .collect::<std::result::Result<Vec<_>, CaptureError>>()?;
+        decoded.push(DecodedCommand { name, fields });
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::null_backend::tests::device;
+
+    fn capture() -> CommandCapture {
+        let mut capture = CommandCapture::default();
+        {
+            let mut command = capture.command(std::ptr::null_mut(), "pipeline_barrier");
+            command.access("src_access", Access::COMPUTE_SHADER_WRITE);
+            command.access("dst_access", Access::TRANSFER_READ);
+        }
+        {
+            let mut command = capture.command(std::ptr::null_mut(), "begin_label");
+            command.str("name", "shadows");
+            command.bytes("data", &[1, 2, 255]);
+        }
+        capture
+    }
+
+    #[test]
+    fn round_trips_and_dumps() {
+        let capture = CommandCapture::from_bytes(&capture().to_bytes()).unwrap();
+        assert_eq!(capture.command_count(), 2);
+        assert_eq!(
+            capture.dump(),
+            "#0 pipeline_barrier\n    src_access: COMPUTE_SHADER_BIT / WRITE\n    dst_access: TRANSFER_BIT / READ\n\
+             #1 begin_label\n    name: \"shadows\"\n    data: 3 bytes [01 02 ff]\n"
+        );
+    }
+
+    #[test]
+    fn numbers_handles_in_order_of_appearance() {
+        let (first, second) = (7, 3);
+        let mut capture = CommandCapture::default();
+        {
+            let mut command = capture.command(std::ptr::null_mut(), "wait_events");
+            command.event("event", second);
+            command.event("event", first);
+            command.timeline_query_pool("query_pool", first);
+            command.event("event", second);
+        }
+        assert_eq!(
+            capture.dump(),
+            "#0 wait_events\n    event: event #0\n    event: event #1\n    \
+             query_pool: timeline query pool #0\n    event: event #0\n"
+        );
+    }
+
+    #[test]
+    fn numbers_reallocated_handles_apart() {
+        let device = device();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        recorder.begin_capture();
+        for _ in 0..2 {
+            // The second event is likely to get the first one's address.
+            let event = device.create_event(&Default::default()).unwrap();
+            recorder.reset_event(&event, PipelineStageFlags::ALL_COMMANDS_BIT);
+        }
+        let dump = recorder.end_capture().unwrap().dump();
+        assert!(dump.contains("event: event #0\n"));
+        assert!(dump.contains("event: event #1\n"));
+    }
+
+    #[test]
+    fn encodes_more_than_u16_max_fields() {
+        let mut capture = CommandCapture::default();
+        {
+            let mut command = capture.command(std::ptr::null_mut(), "many_fields");
+            for index in 0..=u16::MAX as u64 {
+                command.u64("field", index);
+            }
+        }
+        let capture = CommandCapture::from_bytes(&capture.to_bytes()).unwrap();
+        assert_eq!(capture.dump().lines().count(), 1 + u16::MAX as usize + 1);
+    }
+
+    #[test]
+    fn cuts_long_strings_at_char_boundaries() {
+        let mut bytes = Vec::new();
+        write_str(&mut bytes, &"é".repeat(u16::MAX as usize));
+        let len = u16::from_le_bytes([bytes[0], bytes[1]]) as usize;
+        assert_eq!(len, u16::MAX as usize - 1);
+        assert!(std::str::from_utf8(&bytes[2..]).is_ok());
+        assert_eq!(bytes.len(), 2 + len);
+    }
+
+    #[test]
+    fn captures_info_fields_and_resource_names() {
+        let device = device();
+        let buffer = |name: &str| {
+            device
+                .create_buffer(&BufferInfo {
+                    size: 64,
+                    allocate_info: MemoryFlags::empty(),
+                    name: name.into(),
+                })
+                .unwrap()
+                .id()
+        };
+        let (src_buffer, dst_buffer) = (buffer("src"), buffer("dst"));
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        recorder.begin_capture();
+        recorder.copy_buffer_to_buffer(&BufferCopyInfo {
+            src_buffer,
+            dst_buffer,
+            src_offset: 0,
+            dst_offset: 16,
+            size: 32,
+        });
+        recorder.push_constants(&[0u32; 3]);
+        assert_eq!(
+            recorder.end_capture().unwrap().dump(),
+            "#0 copy_buffer_to_buffer\n    src_buffer: buffer 0x1 \"src\"\n    dst_buffer: buffer 0x2 \"dst\"\n    \
+             src_offset: 0\n    dst_offset: 16\n    size: 32\n\
+             #1 push_constants\n    type: \"[u32; 3]\"\n    size: 12\n"
+        );
+    }
+
+    #[test]
+    fn rejects_broken_data() {
+        let bytes = capture().to_bytes();
+        assert!(matches!(
+            CommandCapture::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(CaptureError::Truncated)
+        ));
+        assert!(matches!(
+            CommandCapture::from_bytes(b"not a capture"),
+            Err(CaptureError::NotACapture)
+        ));
+    }
+}
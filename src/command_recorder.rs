@@ -1,10 +1,15 @@
 use bitflags::bitflags;
+use std::cell::{Cell, RefCell};
 use std::mem;
-use std::process::Command;
-use std::sync;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
+use crate::backend as daxa_sys;
+use crate::capture::*;
+use crate::pipeline::MAX_COLOR_ATTACHMENTS;
 use crate::types::*;
 
+#[repr(C)]
 pub struct CommandRecorderInfo
 {
     name: SmallString,
@@ -21,12 +26,121 @@ impl Default for CommandRecorderInfo
     }
 }
 
+#[derive(Debug)]
+pub enum CommandError {
+    Daxa(crate::types::Result),
+    /// `complete_current_commands` was called while labels were still open.
+    UnclosedLabels(u32),
+    /// `end_label` was called without a matching `begin_label`.
+    LabelUnderflow,
+    /// A submission waits on an event that is not signaled earlier in it or by a previous submission.
+    EventWaitWithoutSignal,
+    /// The clear value does not match the [`FormatClass`] of the cleared image's or attachment's format.
+    ClearValueMismatch { format: Format, clear_value: ClearValue },
+    /// A render pass has more than 8 color attachments.
+    TooManyColorAttachments(usize),
+    /// The format can not be blitted from and to with the requested filter.
+    UnsupportedBlitFormat(Format),
+    /// The slice reaches past the mips or array layers of the image.
+    SliceOutOfRange { slice: ImageMipArraySlice, mip_level_count: u32, array_layer_count: u32 },
+    /// A uniform buffer offset is not a multiple of the device's `minUniformBufferOffsetAlignment`.
+    UniformBufferMisaligned { offset: u64, alignment: u64 },
+    /// A uniform buffer binding is larger than the device's `maxUniformBufferRange`.
+    UniformBufferTooLarge { size: u64, max_size: u64 },
+    /// A uniform buffer binding covers no bytes.
+    EmptyUniformBuffer,
+    /// An indirect dispatch or draw was given an empty slice of commands.
+    EmptyIndirectCommands,
+    /// `dispatch_threads` was called without a bound compute pipeline that knows its workgroup size.
+    UnknownWorkgroupSize,
+    /// The bound compute pipeline's workgroup size is 0 along an axis.
+    EmptyWorkgroupSize([u32; 3]),
+    /// The dispatch needs more workgroups than the device's `maxComputeWorkGroupCount` allows.
+    DispatchTooLarge { group_count: [u32; 3], max_group_count: [u32; 3] },
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Daxa(result) => write!(f, "daxa returned {:?}", result),
+            CommandError::UnclosedLabels(count) => {
+                write!(f, "commands were completed with {} debug labels still open", count)
+            }
+            CommandError::LabelUnderflow => write!(f, "end_label was called without a matching begin_label"),
+            CommandError::EventWaitWithoutSignal => {
+                write!(f, "an event is waited on without being signaled earlier in the submission")
+            }
+            CommandError::ClearValueMismatch { format, clear_value } => {
+                write!(f, "clear value {:?} does not match the class of format {:?}", clear_value, format)
+            }
+            CommandError::TooManyColorAttachments(count) => write!(
+                f,
+                "{} color attachments, a render pass has at most {}",
+                count, MAX_COLOR_ATTACHMENTS
+            ),
+            CommandError::UnsupportedBlitFormat(format) => {
+                write!(f, "format {:?} can not be blitted with the requested filter", format)
+            }
+            CommandError::SliceOutOfRange { slice, mip_level_count, array_layer_count } => write!(
+                f,
+                "mips {}..{} and layers {}..{} do not fit an image with {} mips and {} layers",
+                slice.base_mip_level,
+                slice.base_mip_level as u64 + slice.level_count as u64,
+                slice.base_array_layer,
+                slice.base_array_layer as u64 + slice.layer_count as u64,
+                mip_level_count,
+                array_layer_count
+            ),
+            CommandError::UniformBufferMisaligned { offset, alignment } => write!(
+                f,
+                "uniform buffer offset {} is not a multiple of the required alignment {}",
+                offset, alignment
+            ),
+            CommandError::UniformBufferTooLarge { size, max_size } => write!(
+                f,
+                "uniform buffer binding of {} bytes exceeds the device limit of {} bytes",
+                size, max_size
+            ),
+            CommandError::EmptyUniformBuffer => write!(f, "uniform buffer binding is empty"),
+            CommandError::EmptyIndirectCommands => write!(f, "indirect command slice is empty"),
+            CommandError::UnknownWorkgroupSize => {
+                write!(f, "dispatch_threads needs a bound compute pipeline with a known workgroup size")
+            }
+            CommandError::EmptyWorkgroupSize(workgroup_size) => {
+                write!(f, "workgroup size {:?} is 0 along an axis", workgroup_size)
+            }
+            CommandError::DispatchTooLarge { group_count, max_group_count } => write!(
+                f,
+                "dispatch of {:?} workgroups exceeds the device limit of {:?}",
+                group_count, max_group_count
+            ),
+        }
+    }
+}
+impl std::error::Error for CommandError {}
+
+impl From<crate::types::Result> for CommandError {
+    fn from(result: crate::types::Result) -> Self {
+        CommandError::Daxa(result)
+    }
+}
 
 pub struct CommandRecorder
 {
     recorder: daxa_sys::daxa_CommandRecorder,
+    // Used to look up resource infos, like the format of a cleared image.
+    device: daxa_sys::daxa_Device,
+    open_labels: Cell<u32>,
+    event_ops: RefCell<Vec<(EventOp, Arc<AtomicBool>)>>,
+    capture: RefCell<std::option::Option<CommandCapture>>,
+    // Of the bound compute pipeline, if it declared one.
+    compute_workgroup_size: Cell<std::option::Option<[u32; 3]>>,
 }
 
+// A recorder may be moved to another thread, but recording into it is not synchronized,
+// so it is deliberately not Sync.
+unsafe impl Send for CommandRecorder {}
+
 impl Drop for CommandRecorder
 {
     fn drop(&mut self) {
@@ -36,12 +150,102 @@ impl Drop for CommandRecorder
     }
 }
 
+impl CommandRecorder
+{
+    pub(crate) fn new(device: daxa_sys::daxa_Device, recorder: daxa_sys::daxa_CommandRecorder) -> Self
+    {
+        Self{
+            recorder,
+            device,
+            open_labels: Cell::new(0),
+            event_ops: RefCell::new(Vec::new()),
+            capture: RefCell::new(None),
+            compute_workgroup_size: Cell::new(None),
+        }
+    }
+
+    /// Starts capturing every command recorded from now on, see [`CommandCapture`].
+    /// A capture that is already running is discarded. Capturing continues across
+    /// [`Self::complete_current_commands`], so a capture can span a whole frame.
+    pub fn begin_capture(self: &Self)
+    {
+        *self.capture.borrow_mut() = Some(CommandCapture::default());
+    }
+
+    /// Stops capturing and returns everything recorded since [`Self::begin_capture`].
+    pub fn end_capture(self: &Self) -> std::option::Option<CommandCapture>
+    {
+        self.capture.borrow_mut().take()
+    }
+
+    fn capture(self: &Self, command: &str, fields: impl FnOnce(&mut CommandEncoder))
+    {
+        if let Some(capture) = self.capture.borrow_mut().as_mut() {
+            fields(&mut capture.command(self.device, command));
+        }
+    }
+
+    /// Finishes the commands recorded so far into an [`ExecutableCommandList`].
+    /// The recorder stays usable afterwards and starts a fresh list.
+    pub fn complete_current_commands(self: &Self) -> std::result::Result<ExecutableCommandList, CommandError>
+    {
+        let open_labels = self.open_labels.get();
+        if open_labels != 0 {
+            return Err(CommandError::UnclosedLabels(open_labels));
+        }
+        unsafe{
+            let mut executable_commands = mem::zeroed();
+
+            let c_result = daxa_sys::daxa_cmd_complete_current_commands(self.recorder, &mut executable_commands);
+
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(c_result) {
+                Result::Success => Ok(ExecutableCommandList{
+                    handle: executable_commands,
+                    event_ops: self.event_ops.take(),
+                }),
+                error => Err(error.into()),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum EventOp
+{
+    Signal,
+    Wait,
+    Reset,
+}
+
+pub struct ExecutableCommandList
+{
+    pub(crate) handle: daxa_sys::daxa_ExecutableCommandList,
+    // Event signals, waits and resets in recording order, checked against each other on submit.
+    pub(crate) event_ops: Vec<(EventOp, Arc<AtomicBool>)>,
+}
+
+// A completed list is never mutated again, it is only read by submissions and returned to the
+// recorder's pool on destruction, which daxa synchronizes internally.
+unsafe impl Send for ExecutableCommandList {}
+
+impl Drop for ExecutableCommandList
+{
+    fn drop(&mut self) {
+        unsafe{
+            // Submitted lists are kept alive by the device until the gpu is done with them,
+            // unsubmitted ones go straight back into the recorder's command buffer pool.
+            daxa_sys::daxa_destroy_executable_command_list(self.handle);
+        }
+    }
+}
+
 macro_rules! define_command_recorder_function_with_result {
     ($rust_name:ident, $c_name:ident, $info_type:ty) => {
         impl CommandRecorder
         {
             pub fn $rust_name(self: &Self, info: &$info_type) -> daxa_sys::daxa_Result
             {
+                self.capture(stringify!($rust_name), |encoder| info.capture(encoder));
                 unsafe{
                     daxa_sys::$c_name(std::mem::transmute(self.recorder), std::mem::transmute(info))
                 }
@@ -56,6 +260,7 @@ macro_rules! define_command_recorder_function {
         {
             pub fn $rust_name(self: &Self, info: &$info_type)
             {
+                self.capture(stringify!($rust_name), |encoder| info.capture(encoder));
                 unsafe{
                     daxa_sys::$c_name(std::mem::transmute(self.recorder), std::mem::transmute(info))
                 }
@@ -69,39 +274,256 @@ pub type BufferImageCopyInfo = daxa_sys::daxa_BufferImageCopyInfo;
 pub type ImageBufferCopyInfo = daxa_sys::daxa_ImageBufferCopyInfo;
 pub type ImageCopyInfo = daxa_sys::daxa_ImageCopyInfo;
 pub type ImageBlitInfo = daxa_sys::daxa_ImageBlitInfo;
-pub type ImageClearInfo = daxa_sys::daxa_ImageClearInfo;    
 pub type BufferClearInfo = daxa_sys::daxa_BufferClearInfo;
-define_command_recorder_function_with_result!(copy_buffer_to_buffer, daxa_cmd_copy_buffer_to_buffer, BufferInfo);
+define_command_recorder_function_with_result!(copy_buffer_to_buffer, daxa_cmd_copy_buffer_to_buffer, BufferCopyInfo);
 define_command_recorder_function_with_result!(copy_buffer_to_image, daxa_cmd_copy_buffer_to_image, BufferImageCopyInfo);
 define_command_recorder_function_with_result!(copy_image_to_buffer, daxa_cmd_copy_image_to_buffer, ImageBufferCopyInfo);
 define_command_recorder_function_with_result!(copy_image_to_image, daxa_cmd_copy_image_to_image, ImageCopyInfo);
 define_command_recorder_function_with_result!(blit_image_to_image, daxa_cmd_blit_image_to_image, ImageBlitInfo);
-define_command_recorder_function_with_result!(clear_buffer, daxa_cmd_clear_buffer, ImageClearInfo);
-define_command_recorder_function_with_result!(clear_image, daxa_cmd_clear_image, BufferClearInfo);
+define_command_recorder_function_with_result!(clear_buffer, daxa_cmd_clear_buffer, BufferClearInfo);
+
+#[derive(Clone, Copy, Debug)]
+pub struct ImageClearInfo
+{
+    pub image_layout: ImageLayout,
+    pub clear_value: ClearValue,
+    pub image: ImageId,
+    pub dst_slice: ImageMipArraySlice,
+}
+
+impl CommandRecorder
+{
+    /// Fails with [`CommandError::ClearValueMismatch`] instead of clearing to garbage when the clear
+    /// value does not fit the image's format, like floats for an integer image.
+    pub fn clear_image(self: &Self, info: &ImageClearInfo) -> std::result::Result<(), CommandError>
+    {
+        let format = self.image_info(info.image)?.format;
+        if !info.clear_value.is_compatible(format) {
+            return Err(CommandError::ClearValueMismatch{ format, clear_value: info.clear_value });
+        }
+        self.capture("clear_image", |encoder| {
+            encoder.image("image", info.image);
+            encoder.str("image_layout", &format!("{:?}", info.image_layout));
+            encoder.slice("dst_slice", info.dst_slice);
+            encoder.str("clear_value", &format!("{:?}", info.clear_value));
+        });
+        let c_info = daxa_sys::daxa_ImageClearInfo{
+            image_layout: info.image_layout as _,
+            clear_value: info.clear_value.into(),
+            image: info.image,
+            dst_slice: unsafe{ mem::transmute(info.dst_slice) },
+        };
+        unsafe{
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(daxa_sys::daxa_cmd_clear_image(self.recorder, &c_info)) {
+                Result::Success => Ok(()),
+                error => Err(error.into()),
+            }
+        }
+    }
+
+    fn image_view_info(self: &Self, image_view: ImageViewId) -> std::result::Result<ImageViewInfo, CommandError>
+    {
+        unsafe{
+            let mut info = mem::zeroed::<daxa_sys::daxa_ImageViewInfo>();
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(daxa_sys::daxa_dvc_info_image_view(self.device, image_view, &mut info)) {
+                Result::Success => Ok(mem::transmute::<_, ImageViewInfo>(info)),
+                error => Err(error.into()),
+            }
+        }
+    }
+
+    fn image_info(self: &Self, image: ImageId) -> std::result::Result<ImageInfo, CommandError>
+    {
+        unsafe{
+            let mut info = mem::zeroed::<daxa_sys::daxa_ImageInfo>();
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(daxa_sys::daxa_dvc_info_image(self.device, image, &mut info)) {
+                Result::Success => Ok(mem::transmute::<_, ImageInfo>(info)),
+                error => Err(error.into()),
+            }
+        }
+    }
+
+    /// Fills the mips of `slice` after its base mip by repeatedly blitting each mip into the next one,
+    /// for all array layers of the slice at once. Sizes are halved and rounded down per mip, down
+    /// to 1, so non power of two and 3D images work as well.
+    ///
+    /// Expects every mip of `slice` to be in [`ImageLayout::TransferDstOptimal`], with the base mip
+    /// holding the image, and leaves all of them in [`ImageLayout::ReadOnlyOptimal`].
+    /// Fails with [`CommandError::UnsupportedBlitFormat`] for compressed, integer, depth and stencil
+    /// formats, and for other filters than [`Filter::NEAREST`] unless the format is
+    /// [`Format::is_linear_blittable`]. Fails with [`CommandError::SliceOutOfRange`] when `slice`
+    /// reaches past the image's mips or array layers.
+    pub fn generate_mipmaps(self: &Self, image: ImageId, slice: ImageMipArraySlice, filter: Filter) -> std::result::Result<(), CommandError>
+    {
+        let info = self.image_info(image)?;
+        let blittable = info.format.class() == FormatClass::Float
+            && !info.format.is_block_compressed()
+            && (filter == Filter::NEAREST || info.format.is_linear_blittable());
+        if !blittable {
+            return Err(CommandError::UnsupportedBlitFormat(info.format));
+        }
+        // A level count of 0 is treated as the base mip alone below, and so are layer counts here.
+        let mip_end = slice.base_mip_level as u64 + slice.level_count.max(1) as u64;
+        let layer_end = slice.base_array_layer as u64 + slice.layer_count.max(1) as u64;
+        if mip_end > info.mip_level_count as u64 || layer_end > info.array_layer_count as u64 {
+            return Err(CommandError::SliceOutOfRange{
+                slice,
+                mip_level_count: info.mip_level_count,
+                array_layer_count: info.array_layer_count,
+            });
+        }
+
+        let mip_slice = |mip_level: u32| ImageMipArraySlice{
+            base_mip_level: mip_level,
+            level_count: 1,
+            ..slice
+        };
+        let array_slice = |mip_level: u32| daxa_sys::daxa_ImageArraySlice{
+            mip_level,
+            base_array_layer: slice.base_array_layer,
+            layer_count: slice.layer_count,
+        };
+        let mip_extent = |mip_level: u32| daxa_sys::VkOffset3D{
+            x: (info.size.width >> mip_level).max(1) as i32,
+            y: (info.size.height >> mip_level).max(1) as i32,
+            z: (info.size.depth >> mip_level).max(1) as i32,
+        };
+        let transition = |mip_level: u32, src_access: Access, src_layout: ImageLayout, dst_access: Access, dst_layout: ImageLayout| {
+            let result = self.pipeline_barrier_image_transition(&ImageMemoryBarrierInfo{
+                src_access,
+                dst_access,
+                src_layout,
+                dst_layout,
+                image_slice: mip_slice(mip_level),
+                image_id: image,
+            });
+            match unsafe{ mem::transmute::<daxa_sys::daxa_Result, Result>(result) } {
+                Result::Success => Ok(()),
+                error => Err(CommandError::from(error)),
+            }
+        };
+
+        let first_mip = slice.base_mip_level;
+        let last_mip = first_mip + slice.level_count.max(1) - 1;
+        let origin = daxa_sys::VkOffset3D{ x: 0, y: 0, z: 0 };
+        for mip_level in first_mip..last_mip {
+            transition(mip_level, Access::TRANSFER_WRITE, ImageLayout::TransferDstOptimal, Access::TRANSFER_READ, ImageLayout::TransferSrcOptimal)?;
+            let result = self.blit_image_to_image(&daxa_sys::daxa_ImageBlitInfo{
+                src_image: image,
+                src_image_layout: ImageLayout::TransferSrcOptimal as _,
+                dst_image: image,
+                dst_image_layout: ImageLayout::TransferDstOptimal as _,
+                src_slice: array_slice(mip_level),
+                src_offsets: [origin, mip_extent(mip_level)],
+                dst_slice: array_slice(mip_level + 1),
+                dst_offsets: [origin, mip_extent(mip_level + 1)],
+                filter: filter.bits(),
+            });
+            match unsafe{ mem::transmute::<daxa_sys::daxa_Result, Result>(result) } {
+                Result::Success => {},
+                error => return Err(error.into()),
+            }
+            transition(mip_level, Access::TRANSFER_READ, ImageLayout::TransferSrcOptimal, Access::READ, ImageLayout::ReadOnlyOptimal)?;
+        }
+        transition(last_mip, Access::TRANSFER_WRITE, ImageLayout::TransferDstOptimal, Access::READ, ImageLayout::ReadOnlyOptimal)
+    }
+}
 
 
-pub type MemoryBarrierInfo = daxa_sys::daxa_MemoryBarrierInfo;
-pub type ImageMemoryBarrierInfo = daxa_sys::daxa_ImageMemoryBarrierInfo;
-pub type EventSignalInfo = daxa_sys::daxa_EventSignalInfo;
-pub type EventWaitInfo = daxa_sys::daxa_EventWaitInfo;
-pub type ResetEventInfo = daxa_sys::daxa_ResetEventInfo;
 define_command_recorder_function!(pipeline_barrier, daxa_cmd_pipeline_barrier, MemoryBarrierInfo);
 define_command_recorder_function_with_result!(pipeline_barrier_image_transition, daxa_cmd_pipeline_barrier_image_transition, ImageMemoryBarrierInfo);
-define_command_recorder_function!(signal_event, daxa_cmd_signal_event, EventSignalInfo);
-define_command_recorder_function!(wait_event, daxa_cmd_wait_event, EventWaitInfo);
-define_command_recorder_function!(reset_event, daxa_cmd_reset_event, ResetEventInfo);
+
+/// The barriers a split barrier applies between its signal and its wait.
+#[derive(Clone, Copy, Default)]
+pub struct DependencyInfo<'a>
+{
+    pub memory_barriers: &'a [MemoryBarrierInfo],
+    pub image_memory_barriers: &'a [ImageMemoryBarrierInfo],
+}
+
+impl<'a> DependencyInfo<'a>
+{
+    fn capture(&self, encoder: &mut CommandEncoder, event: &Event)
+    {
+        encoder.event("event", event.serial);
+        for (index, barrier) in self.memory_barriers.iter().enumerate() {
+            encoder.access(&format!("memory_barriers[{}].src_access", index), barrier.src_access);
+            encoder.access(&format!("memory_barriers[{}].dst_access", index), barrier.dst_access);
+        }
+        for (index, barrier) in self.image_memory_barriers.iter().enumerate() {
+            let prefix = format!("image_memory_barriers[{}]", index);
+            encoder.access(&format!("{}.src_access", prefix), barrier.src_access);
+            encoder.access(&format!("{}.dst_access", prefix), barrier.dst_access);
+            encoder.str(&format!("{}.src_layout", prefix), &format!("{:?}", barrier.src_layout));
+            encoder.str(&format!("{}.dst_layout", prefix), &format!("{:?}", barrier.dst_layout));
+            encoder.slice(&format!("{}.image_slice", prefix), barrier.image_slice);
+            encoder.image(&format!("{}.image", prefix), barrier.image_id);
+        }
+    }
+
+    fn c_info(&self, event: &Event) -> daxa_sys::daxa_EventSignalInfo
+    {
+        daxa_sys::daxa_EventSignalInfo{
+            memory_barriers: self.memory_barriers.as_ptr().cast(),
+            memory_barrier_count: self.memory_barriers.len() as _,
+            image_memory_barriers: self.image_memory_barriers.as_ptr().cast(),
+            image_memory_barrier_count: self.image_memory_barriers.len() as _,
+            event: &event.handle as *const _ as *mut _,
+        }
+    }
+}
+
 impl CommandRecorder
 {
-    pub fn wait_events(self: &Self, info: &[EventWaitInfo])
+    pub fn signal_event(self: &Self, event: &Event, dependency: &DependencyInfo)
     {
-        let ptr_range = info.as_ptr_range();
-        let size = (ptr_range.end as usize - ptr_range.start as usize) / std::mem::size_of::<EventWaitInfo>();
+        self.capture("signal_event", |encoder| dependency.capture(encoder, event));
+        let info = dependency.c_info(event);
         unsafe{
-            daxa_sys::daxa_cmd_wait_events(
-                std::mem::transmute(self.recorder), 
-                std::mem::transmute(ptr_range.start), 
-                size);
+            daxa_sys::daxa_cmd_signal_event(self.recorder, &info);
         }
+        self.event_ops.borrow_mut().push((EventOp::Signal, event.signaled.clone()));
+    }
+
+    /// `dependency` has to match the one the event was signaled with.
+    pub fn wait_event(self: &Self, event: &Event, dependency: &DependencyInfo)
+    {
+        self.wait_events(&[(event, *dependency)]);
+    }
+
+    pub fn wait_events(self: &Self, waits: &[(&Event, DependencyInfo)])
+    {
+        self.capture("wait_events", |encoder| {
+            for (event, dependency) in waits {
+                dependency.capture(encoder, event);
+            }
+        });
+        let infos = waits
+            .iter()
+            .map(|(event, dependency)| dependency.c_info(event))
+            .collect::<Vec<_>>();
+        unsafe{
+            daxa_sys::daxa_cmd_wait_events(self.recorder, infos.as_ptr(), infos.len() as _);
+        }
+        self.event_ops
+            .borrow_mut()
+            .extend(waits.iter().map(|(event, _)| (EventOp::Wait, event.signaled.clone())));
+    }
+
+    pub fn reset_event(self: &Self, event: &Event, stages: PipelineStageFlags)
+    {
+        self.capture("reset_event", |encoder| {
+            encoder.event("event", event.serial);
+            encoder.stages("stages", stages);
+        });
+        let info = daxa_sys::daxa_ResetEventInfo{
+            barrier: &event.handle as *const _ as *mut _,
+            stage_masks: stages.bits(),
+        };
+        unsafe{
+            daxa_sys::daxa_cmd_reset_event(self.recorder, &info);
+        }
+        self.event_ops.borrow_mut().push((EventOp::Reset, event.signaled.clone()));
     }
 }
 
@@ -110,6 +532,11 @@ impl CommandRecorder
 {
     pub fn push_constants<T>(self: &Self, data: &T)
     {
+        // Only the type is captured, reading the bytes of `T` could read its padding.
+        self.capture("push_constants", |encoder| {
+            encoder.str("type", std::any::type_name::<T>());
+            encoder.u64("size", std::mem::size_of::<T>() as u64);
+        });
         unsafe{
             let ptr = std::mem::transmute(data);
             let size = std::mem::size_of::<T>();
@@ -122,8 +549,760 @@ impl CommandRecorder
 }
 define_command_recorder_function_with_result!(set_uniform_buffer, daxa_cmd_set_uniform_buffer, SetUniformBufferInfo);
 
+impl CommandRecorder
+{
+    /// Binds the elements of `uniform` to `slot`. A whole [`TypedBuffer`] binds its first element.
+    ///
+    /// The offset has to be a multiple of `minUniformBufferOffsetAlignment` and the size must not
+    /// exceed `maxUniformBufferRange`, both from the device limits. Empty bindings are rejected.
+    pub fn set_typed_uniform_buffer<T>(self: &Self, slot: u32, uniform: impl Into<TypedBufferSlice<T>>) -> std::result::Result<(), CommandError>
+    {
+        let uniform = uniform.into();
+        let limits = unsafe{ &(*daxa_sys::daxa_dvc_properties(self.device)).limits };
+        let offset = uniform.byte_offset() as u64;
+        let size = uniform.byte_size() as u64;
+        let alignment = limits.minUniformBufferOffsetAlignment as u64;
+        if size == 0 {
+            return Err(CommandError::EmptyUniformBuffer);
+        }
+        if alignment != 0 && !offset.is_multiple_of(alignment) {
+            return Err(CommandError::UniformBufferMisaligned{ offset, alignment });
+        }
+        if size > limits.maxUniformBufferRange as u64 {
+            return Err(CommandError::UniformBufferTooLarge{ size, max_size: limits.maxUniformBufferRange as u64 });
+        }
+        self.capture("set_uniform_buffer", |encoder| {
+            encoder.u64("slot", slot as u64);
+            encoder.buffer("buffer", uniform.id());
+            encoder.u64("offset", offset);
+            encoder.u64("size", size);
+        });
+        let info = daxa_sys::daxa_SetUniformBufferInfo{
+            slot,
+            buffer: uniform.id(),
+            size: size as _,
+            offset: offset as _,
+        };
+        unsafe{
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(daxa_sys::daxa_cmd_set_uniform_buffer(self.recorder, &info)) {
+                Result::Success => Ok(()),
+                error => Err(error.into()),
+            }
+        }
+    }
+}
+
 pub type DispatchInfo = daxa_sys::daxa_DispatchInfo;
-pub type DispatchIndirectInfo = daxa_sys::daxa_DispatchIndirectInfo;
 define_command_recorder_function!(dispatch, daxa_cmd_dispatch, DispatchInfo);
-define_command_recorder_function_with_result!(dispatch_indirect, daxa_cmd_dispatch_indirect, DispatchIndirectInfo);
-define_command_recorder_function!(set_compute_pipeline, daxa_cmd_set_compute_pipeline, ComputePipeline);
+
+impl CommandRecorder
+{
+    /// Dispatches enough workgroups of the bound compute pipeline to cover `x * y * z` threads.
+    /// Shaders have to skip the threads past the requested count themselves.
+    pub fn dispatch_threads(self: &Self, x: u32, y: u32, z: u32) -> std::result::Result<(), CommandError>
+    {
+        let workgroup_size = self.compute_workgroup_size.get().ok_or(CommandError::UnknownWorkgroupSize)?;
+        if workgroup_size.contains(&0) {
+            return Err(CommandError::EmptyWorkgroupSize(workgroup_size));
+        }
+        let threads = [x, y, z];
+        let group_count: [u32; 3] = std::array::from_fn(|axis| threads[axis].div_ceil(workgroup_size[axis]));
+        let max_group_count = unsafe{ (*daxa_sys::daxa_dvc_properties(self.device)).limits.maxComputeWorkGroupCount };
+        if group_count.iter().zip(max_group_count).any(|(&count, max)| count > max) {
+            return Err(CommandError::DispatchTooLarge{ group_count, max_group_count });
+        }
+        let [x, y, z] = group_count;
+        self.dispatch(&DispatchInfo{ x, y, z });
+        Ok(())
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DispatchIndirectCommand
+{
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DrawIndirectCommand
+{
+    pub vertex_count: u32,
+    pub instance_count: u32,
+    pub first_vertex: u32,
+    pub first_instance: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DrawIndexedIndirectCommand
+{
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub vertex_offset: i32,
+    pub first_instance: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DrawMeshTasksIndirectCommand
+{
+    pub group_count_x: u32,
+    pub group_count_y: u32,
+    pub group_count_z: u32,
+}
+
+const _: () = assert!(mem::size_of::<DispatchIndirectCommand>() == mem::size_of::<daxa_sys::VkDispatchIndirectCommand>());
+const _: () = assert!(mem::size_of::<DrawIndirectCommand>() == mem::size_of::<daxa_sys::VkDrawIndirectCommand>());
+const _: () = assert!(mem::size_of::<DrawIndexedIndirectCommand>() == mem::size_of::<daxa_sys::VkDrawIndexedIndirectCommand>());
+const _: () = assert!(mem::size_of::<DrawMeshTasksIndirectCommand>() == mem::size_of::<daxa_sys::VkDrawMeshTasksIndirectCommandEXT>());
+
+impl CommandRecorder
+{
+    /// Dispatches with the group counts stored in the first command of `command`.
+    pub fn dispatch_indirect(self: &Self, command: TypedBufferSlice<DispatchIndirectCommand>) -> std::result::Result<(), CommandError>
+    {
+        if command.is_empty() {
+            return Err(CommandError::EmptyIndirectCommands);
+        }
+        self.capture("dispatch_indirect", |encoder| {
+            encoder.buffer("buffer", command.id());
+            encoder.u64("offset", command.byte_offset() as u64);
+        });
+        let info = daxa_sys::daxa_DispatchIndirectInfo{
+            indirect_buffer: command.id(),
+            offset: command.byte_offset() as _,
+        };
+        unsafe{
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(daxa_sys::daxa_cmd_dispatch_indirect(self.recorder, &info)) {
+                Result::Success => Ok(()),
+                error => Err(error.into()),
+            }
+        }
+    }
+
+    /// Draws once per command in `commands`.
+    pub fn draw_indirect(self: &Self, commands: TypedBufferSlice<DrawIndirectCommand>) -> std::result::Result<(), CommandError>
+    {
+        self.draw_indirect_raw(commands.id(), commands.byte_offset(), commands.len(), mem::size_of::<DrawIndirectCommand>(), false)
+    }
+
+    /// Draws once per command in `commands`, using the bound index buffer.
+    pub fn draw_indexed_indirect(self: &Self, commands: TypedBufferSlice<DrawIndexedIndirectCommand>) -> std::result::Result<(), CommandError>
+    {
+        self.draw_indirect_raw(commands.id(), commands.byte_offset(), commands.len(), mem::size_of::<DrawIndexedIndirectCommand>(), true)
+    }
+
+    fn draw_indirect_raw(self: &Self, buffer: BufferId, offset: usize, count: usize, stride: usize, is_indexed: bool) -> std::result::Result<(), CommandError>
+    {
+        if count == 0 {
+            return Err(CommandError::EmptyIndirectCommands);
+        }
+        let command = if is_indexed { "draw_indexed_indirect" } else { "draw_indirect" };
+        self.capture(command, |encoder| {
+            encoder.buffer("buffer", buffer);
+            encoder.u64("offset", offset as u64);
+            encoder.u64("count", count as u64);
+        });
+        let info = daxa_sys::daxa_DrawIndirectInfo{
+            draw_command_buffer: buffer,
+            draw_command_buffer_read_offset: offset as _,
+            draw_count: count as _,
+            draw_command_stride: stride as _,
+            is_indexed: is_indexed as _,
+        };
+        unsafe{
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(daxa_sys::daxa_cmd_draw_indirect(self.recorder, &info)) {
+                Result::Success => Ok(()),
+                error => Err(error.into()),
+            }
+        }
+    }
+
+    pub fn draw_mesh_tasks_indirect(self: &Self, commands: TypedBufferSlice<DrawMeshTasksIndirectCommand>) -> std::result::Result<(), CommandError>
+    {
+        if commands.is_empty() {
+            return Err(CommandError::EmptyIndirectCommands);
+        }
+        self.capture("draw_mesh_tasks_indirect", |encoder| {
+            encoder.buffer("buffer", commands.id());
+            encoder.u64("offset", commands.byte_offset() as u64);
+            encoder.u64("count", commands.len() as u64);
+        });
+        let info = daxa_sys::daxa_DrawMeshTasksIndirectInfo{
+            indirect_buffer: commands.id(),
+            offset: commands.byte_offset() as _,
+            draw_count: commands.len() as _,
+            stride: mem::size_of::<DrawMeshTasksIndirectCommand>() as _,
+        };
+        unsafe{
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(daxa_sys::daxa_cmd_draw_mesh_tasks_indirect(self.recorder, &info)) {
+                Result::Success => Ok(()),
+                error => Err(error.into()),
+            }
+        }
+    }
+}
+impl CommandRecorder
+{
+    pub fn set_compute_pipeline(self: &Self, pipeline: &ComputePipeline)
+    {
+        self.capture("set_compute_pipeline", |encoder| encoder.compute_pipeline("pipeline", pipeline.serial));
+        unsafe{
+            daxa_sys::daxa_cmd_set_compute_pipeline(self.recorder, pipeline.handle);
+        }
+        self.compute_workgroup_size.set(pipeline.workgroup_size);
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RenderAttachmentInfo
+{
+    pub image_view: ImageViewId,
+    pub layout: ImageLayout,
+    pub load_op: AttachmentLoadOp,
+    pub store_op: AttachmentStoreOp,
+    /// Only used with [`AttachmentLoadOp::Clear`].
+    pub clear_value: ClearValue,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RenderPassBeginInfo<'a>
+{
+    /// At most 8.
+    pub color_attachments: &'a [RenderAttachmentInfo],
+    pub depth_attachment: std::option::Option<RenderAttachmentInfo>,
+    pub stencil_attachment: std::option::Option<RenderAttachmentInfo>,
+    pub render_area: Rect2D,
+}
+
+#[repr(C)]
+struct CRenderAttachmentInfo
+{
+    image_view: ImageViewId,
+    layout: ImageLayout,
+    load_op: AttachmentLoadOp,
+    store_op: AttachmentStoreOp,
+    clear_value: daxa_sys::VkClearValue,
+}
+
+#[repr(C)]
+struct CRenderPassBeginInfo
+{
+    color_attachments: [CRenderAttachmentInfo; MAX_COLOR_ATTACHMENTS],
+    color_attachments_count: u64,
+    depth_attachment: crate::types::Option<CRenderAttachmentInfo>,
+    stencil_attachment: crate::types::Option<CRenderAttachmentInfo>,
+    render_area: Rect2D,
+}
+
+const _: () = assert!(mem::size_of::<CRenderAttachmentInfo>() == mem::size_of::<daxa_sys::daxa_RenderAttachmentInfo>());
+const _: () = assert!(mem::size_of::<CRenderPassBeginInfo>() == mem::size_of::<daxa_sys::daxa_RenderPassBeginInfo>());
+
+impl From<&RenderAttachmentInfo> for CRenderAttachmentInfo
+{
+    fn from(info: &RenderAttachmentInfo) -> Self {
+        Self{
+            image_view: info.image_view,
+            layout: info.layout,
+            load_op: info.load_op,
+            store_op: info.store_op,
+            clear_value: info.clear_value.into(),
+        }
+    }
+}
+
+impl CommandRecorder
+{
+    /// Fails with [`CommandError::ClearValueMismatch`] when an attachment is cleared with a value
+    /// that does not fit the format of its image view, see [`Self::clear_image`].
+    pub fn begin_renderpass(self: &Self, info: &RenderPassBeginInfo) -> std::result::Result<(), CommandError>
+    {
+        if info.color_attachments.len() > MAX_COLOR_ATTACHMENTS {
+            return Err(CommandError::TooManyColorAttachments(info.color_attachments.len()));
+        }
+        let attachments = info.color_attachments.iter().chain(&info.depth_attachment).chain(&info.stencil_attachment);
+        for attachment in attachments.filter(|attachment| attachment.load_op == AttachmentLoadOp::Clear) {
+            let format = self.image_view_info(attachment.image_view)?.format;
+            if !attachment.clear_value.is_compatible(format) {
+                return Err(CommandError::ClearValueMismatch{ format, clear_value: attachment.clear_value });
+            }
+        }
+        self.capture("begin_renderpass", |encoder| {
+            let attachments = info.color_attachments.iter().enumerate().map(|(index, attachment)| (format!("color_attachments[{}]", index), attachment));
+            let attachments = attachments
+                .chain(info.depth_attachment.iter().map(|attachment| ("depth_attachment".to_owned(), attachment)))
+                .chain(info.stencil_attachment.iter().map(|attachment| ("stencil_attachment".to_owned(), attachment)));
+            for (prefix, attachment) in attachments {
+                encoder.image_view(&format!("{}.image_view", prefix), attachment.image_view);
+                encoder.str(&format!("{}.layout", prefix), &format!("{:?}", attachment.layout));
+                encoder.str(&format!("{}.load_op", prefix), &format!("{:?}", attachment.load_op));
+                encoder.str(&format!("{}.store_op", prefix), &format!("{:?}", attachment.store_op));
+                encoder.str(&format!("{}.clear_value", prefix), &format!("{:?}", attachment.clear_value));
+            }
+            encoder.str("render_area", &format!("{:?}", info.render_area));
+        });
+        unsafe{
+            let mut c_info = CRenderPassBeginInfo{
+                color_attachments: mem::zeroed(),
+                color_attachments_count: info.color_attachments.len() as u64,
+                depth_attachment: info.depth_attachment.as_ref().map(CRenderAttachmentInfo::from).into(),
+                stencil_attachment: info.stencil_attachment.as_ref().map(CRenderAttachmentInfo::from).into(),
+                render_area: info.render_area,
+            };
+            for (c_attachment, attachment) in c_info.color_attachments.iter_mut().zip(info.color_attachments) {
+                *c_attachment = attachment.into();
+            }
+            let c_result = daxa_sys::daxa_cmd_begin_renderpass(
+                self.recorder,
+                (&c_info as *const CRenderPassBeginInfo).cast::<daxa_sys::daxa_RenderPassBeginInfo>(),
+            );
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(c_result) {
+                Result::Success => Ok(()),
+                error => Err(error.into()),
+            }
+        }
+    }
+
+    pub fn end_renderpass(self: &Self)
+    {
+        self.capture("end_renderpass", |_| {});
+        unsafe{
+            daxa_sys::daxa_cmd_end_renderpass(self.recorder);
+        }
+    }
+}
+
+#[repr(C)]
+struct CommandLabelInfo
+{
+    label_color: Vec4<f32>,
+    name: SmallString,
+}
+
+impl CommandRecorder
+{
+    /// Opens a debug label that shows up in RenderDoc, Nsight and validation messages.
+    /// Every label has to be closed with [`Self::end_label`] before the commands are completed.
+    /// Names longer than [`SmallString::MAX_LEN`] bytes are truncated.
+    pub fn begin_label(self: &Self, name: &str, color: [f32; 4])
+    {
+        self.capture("begin_label", |encoder| {
+            encoder.str("name", name);
+            for (component, value) in ["r", "g", "b", "a"].iter().zip(color) {
+                encoder.f32(component, value);
+            }
+        });
+        let [x, y, z, w] = color;
+        let info = CommandLabelInfo{
+            label_color: Vec4{ x, y, z, w },
+            name: SmallString::truncated(name),
+        };
+        unsafe{
+            daxa_sys::daxa_cmd_begin_label(self.recorder, (&info as *const CommandLabelInfo).cast());
+        }
+        self.open_labels.set(self.open_labels.get() + 1);
+    }
+
+    pub fn end_label(self: &Self) -> std::result::Result<(), CommandError>
+    {
+        let open_labels = self.open_labels.get();
+        if open_labels == 0 {
+            return Err(CommandError::LabelUnderflow);
+        }
+        self.capture("end_label", |_| {});
+        unsafe{
+            daxa_sys::daxa_cmd_end_label(self.recorder);
+        }
+        self.open_labels.set(open_labels - 1);
+        Ok(())
+    }
+
+    /// Opens a label that is closed again when the returned guard is dropped.
+    pub fn label_scope<'a>(self: &'a Self, name: &str, color: [f32; 4]) -> LabelScope<'a>
+    {
+        self.begin_label(name, color);
+        LabelScope{ recorder: self }
+    }
+}
+
+pub struct LabelScope<'a>
+{
+    recorder: &'a CommandRecorder,
+}
+
+impl<'a> std::ops::Deref for LabelScope<'a>
+{
+    type Target = CommandRecorder;
+
+    fn deref(&self) -> &Self::Target {
+        self.recorder
+    }
+}
+
+impl<'a> Drop for LabelScope<'a>
+{
+    fn drop(&mut self) {
+        // Only fails if the label was already closed by hand inside of the scope.
+        let _ = self.recorder.end_label();
+    }
+}
+
+impl CommandRecorder
+{
+    pub fn write_timestamp(self: &Self, query_pool: &TimelineQueryPool, stage: PipelineStageFlags, query_index: u32)
+    {
+        self.capture("write_timestamp", |encoder| {
+            encoder.timeline_query_pool("query_pool", query_pool.serial);
+            encoder.stages("stage", stage);
+            encoder.u64("query_index", query_index as u64);
+        });
+        let info = daxa_sys::daxa_WriteTimestampInfo{
+            query_pool: query_pool.handle,
+            pipeline_stage: stage.bits(),
+            query_index,
+        };
+        unsafe{
+            daxa_sys::daxa_cmd_write_timestamp(self.recorder, &info);
+        }
+    }
+
+    /// Queries have to be reset before they can be written again.
+    pub fn reset_timestamps(self: &Self, query_pool: &TimelineQueryPool, start_index: u32, count: u32)
+    {
+        self.capture("reset_timestamps", |encoder| {
+            encoder.timeline_query_pool("query_pool", query_pool.serial);
+            encoder.u64("start_index", start_index as u64);
+            encoder.u64("count", count as u64);
+        });
+        let info = daxa_sys::daxa_ResetTimestampsInfo{
+            query_pool: query_pool.handle,
+            start_index,
+            count,
+        };
+        unsafe{
+            daxa_sys::daxa_cmd_reset_timestamps(self.recorder, &info);
+        }
+    }
+}
+
+macro_rules! define_command_recorder_destroy_deferred {
+    ($name:ident, $type:ident) => {
+        paste::item! {
+            impl CommandRecorder
+            {
+                /// Queues the destruction until the submission containing the commands recorded so far
+                /// has finished on the gpu. [`crate::Device::collect_garbage`] frees it after that,
+                /// so it is safe to call while commands using it are still in flight.
+                pub fn [< destroy_ $name _deferred >](self: &Self, id: [< $type Id >]) -> std::result::Result<(), CommandError>
+                {
+                    self.capture(stringify!([< destroy_ $name _deferred >]), |encoder| encoder.$name("id", id));
+                    unsafe{
+                        match mem::transmute::<daxa_sys::daxa_Result, Result>(daxa_sys::[< daxa_cmd_destroy_ $name _deferred >](self.recorder, id)) {
+                            Result::Success => Ok(()),
+                            error => Err(error.into()),
+                        }
+                    }
+                }
+            }
+
+            impl $type
+            {
+                #[doc = "Like [`CommandRecorder::destroy_" $name "_deferred`], but takes the owning handle"]
+                /// returned by the device, so it can not be used after its destruction was queued.
+                pub fn destroy_deferred(self, recorder: &CommandRecorder) -> std::result::Result<(), CommandError>
+                {
+                    recorder.[< destroy_ $name _deferred >](self.id())
+                }
+            }
+        }
+    };
+}
+
+define_command_recorder_destroy_deferred!(buffer, Buffer);
+define_command_recorder_destroy_deferred!(image, Image);
+define_command_recorder_destroy_deferred!(image_view, ImageView);
+define_command_recorder_destroy_deferred!(sampler, Sampler);
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::null_backend::tests::{device, image, live_command_lists};
+    use crate::{ComputePipelineInfo, SpirV};
+
+    #[test]
+    fn balances_labels()
+    {
+        let device = device();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        assert!(matches!(recorder.end_label(), Err(CommandError::LabelUnderflow)));
+
+        recorder.begin_label("outer", [1.0; 4]);
+        {
+            let _inner = recorder.label_scope("inner", [1.0; 4]);
+        }
+        assert!(matches!(recorder.complete_current_commands(), Err(CommandError::UnclosedLabels(1))));
+        recorder.end_label().unwrap();
+        recorder.complete_current_commands().unwrap();
+    }
+
+    #[test]
+    fn truncates_long_label_names()
+    {
+        let device = device();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        recorder.begin_label(&"label ".repeat(20), [1.0; 4]);
+        recorder.end_label().unwrap();
+
+        let name = format!("{}é", "a".repeat(SmallString::MAX_LEN - 1));
+        assert_eq!(SmallString::truncated(&name).as_str(), &name[..SmallString::MAX_LEN - 1]);
+    }
+
+    #[test]
+    fn rejects_clear_values_of_the_wrong_class()
+    {
+        let device = device();
+        let image = image(&device, Format::R32Uint, 16, 1);
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        let clear = |clear_value| recorder.clear_image(&ImageClearInfo{
+            image_layout: ImageLayout::TransferDstOptimal,
+            clear_value,
+            image,
+            dst_slice: ImageMipArraySlice{ base_mip_level: 0, level_count: 1, base_array_layer: 0, layer_count: 1 },
+        });
+        assert!(matches!(
+            clear(ClearValue::Float([0.0; 4])),
+            Err(CommandError::ClearValueMismatch{ format: Format::R32Uint, .. })
+        ));
+        clear(ClearValue::Uint([0; 4])).unwrap();
+    }
+
+    #[test]
+    fn rejects_attachment_clear_values_of_the_wrong_class()
+    {
+        let device = device();
+        let view = |format, image| device.create_image_view(&ImageViewInfo{
+            ty: ImageViewType::TWO_DIM,
+            format,
+            image,
+            slice: ImageMipArraySlice{ base_mip_level: 0, level_count: 1, base_array_layer: 0, layer_count: 1 },
+            name: "view".into(),
+        }).unwrap().id();
+        let color = view(Format::R32Uint, image(&device, Format::R32Uint, 16, 1));
+        let depth = view(Format::D32Sfloat, image(&device, Format::D32Sfloat, 16, 1));
+        let attachment = |image_view, load_op, clear_value| RenderAttachmentInfo{
+            image_view,
+            layout: ImageLayout::AttachmentOptimal,
+            load_op,
+            store_op: AttachmentStoreOp::Store,
+            clear_value,
+        };
+        let float = ClearValue::Float([0.0; 4]);
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        let begin = |color_attachments: &[RenderAttachmentInfo], depth_attachment| recorder.begin_renderpass(&RenderPassBeginInfo{
+            color_attachments,
+            depth_attachment,
+            stencil_attachment: None,
+            render_area: Rect2D{
+                offset: daxa_sys::VkOffset2D{ x: 0, y: 0 },
+                extent: daxa_sys::VkExtent2D{ width: 16, height: 16 },
+            },
+        });
+
+        assert!(matches!(
+            begin(&[attachment(color, AttachmentLoadOp::Clear, float)], None),
+            Err(CommandError::ClearValueMismatch{ format: Format::R32Uint, .. })
+        ));
+        assert!(matches!(
+            begin(&[], Some(attachment(depth, AttachmentLoadOp::Clear, float))),
+            Err(CommandError::ClearValueMismatch{ format: Format::D32Sfloat, .. })
+        ));
+        assert!(matches!(
+            begin(&[attachment(color, AttachmentLoadOp::Load, float); MAX_COLOR_ATTACHMENTS + 1], None),
+            Err(CommandError::TooManyColorAttachments(9))
+        ));
+        // Clear values are ignored unless the attachment is cleared.
+        begin(&[attachment(color, AttachmentLoadOp::Load, float)], None).unwrap();
+        recorder.end_renderpass();
+        begin(
+            &[attachment(color, AttachmentLoadOp::Clear, ClearValue::Uint([0; 4]))],
+            Some(attachment(depth, AttachmentLoadOp::Clear, ClearValue::DepthStencil{ depth: 1.0, stencil: 0 })),
+        ).unwrap();
+        recorder.end_renderpass();
+    }
+
+    #[test]
+    fn checks_uniform_buffer_bindings()
+    {
+        let device = device();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        // The null device requires 256 byte alignment and allows up to 65536 bytes.
+        let uniforms = TypedBuffer::<[u8; 16]>::new(BufferId{ value: 1 });
+        recorder.set_typed_uniform_buffer(0, uniforms).unwrap();
+        recorder.set_typed_uniform_buffer(0, uniforms.slice(16..16 + 4096)).unwrap();
+        assert!(matches!(
+            recorder.set_typed_uniform_buffer(0, uniforms.at(1)),
+            Err(CommandError::UniformBufferMisaligned{ offset: 16, alignment: 256 })
+        ));
+        assert!(matches!(
+            recorder.set_typed_uniform_buffer(0, uniforms.slice(0..4097)),
+            Err(CommandError::UniformBufferTooLarge{ size: 65552, max_size: 65536 })
+        ));
+        assert!(matches!(recorder.set_typed_uniform_buffer(0, uniforms.slice(16..16)), Err(CommandError::EmptyUniformBuffer)));
+        let zero_sized = TypedBuffer::<()>::new(BufferId{ value: 1 });
+        assert!(matches!(recorder.set_typed_uniform_buffer(0, zero_sized), Err(CommandError::EmptyUniformBuffer)));
+    }
+
+    #[test]
+    fn rejects_empty_indirect_commands()
+    {
+        let device = device();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        let dispatches = TypedBuffer::<DispatchIndirectCommand>::new(BufferId{ value: 1 });
+        let draws = TypedBuffer::<DrawIndirectCommand>::new(BufferId{ value: 2 });
+        let indexed_draws = TypedBuffer::<DrawIndexedIndirectCommand>::new(BufferId{ value: 3 });
+        let mesh_draws = TypedBuffer::<DrawMeshTasksIndirectCommand>::new(BufferId{ value: 4 });
+        recorder.dispatch_indirect(dispatches.at(0)).unwrap();
+        recorder.draw_indirect(draws.slice(0..2)).unwrap();
+        recorder.draw_indexed_indirect(indexed_draws.slice(0..2)).unwrap();
+        recorder.draw_mesh_tasks_indirect(mesh_draws.slice(0..2)).unwrap();
+        assert!(matches!(recorder.dispatch_indirect(dispatches.slice(1..1)), Err(CommandError::EmptyIndirectCommands)));
+        assert!(matches!(recorder.draw_indirect(draws.slice(2..1)), Err(CommandError::EmptyIndirectCommands)));
+        assert!(matches!(recorder.draw_indexed_indirect(indexed_draws.slice(0..0)), Err(CommandError::EmptyIndirectCommands)));
+        assert!(matches!(recorder.draw_mesh_tasks_indirect(mesh_draws.slice(0..0)), Err(CommandError::EmptyIndirectCommands)));
+    }
+
+    #[test]
+    fn dispatches_enough_workgroups_for_all_threads()
+    {
+        let device = device();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        assert!(matches!(recorder.dispatch_threads(1, 1, 1), Err(CommandError::UnknownWorkgroupSize)));
+
+        // Declares a workgroup size of 8x4x1.
+        let spirv = SpirV::from_words(&crate::reflection::tests::compute_shader()).unwrap();
+        let info = ComputePipelineInfo::new(spirv.shader_info("main")).push_constant_size(40);
+        recorder.set_compute_pipeline(&device.create_compute_pipeline(&info).unwrap());
+        recorder.begin_capture();
+        recorder.dispatch_threads(17, 4, 3).unwrap();
+        recorder.dispatch_threads(0, 1, 1).unwrap();
+        assert_eq!(
+            recorder.end_capture().unwrap().dump(),
+            "#0 dispatch\n    x: 3\n    y: 1\n    z: 3\n#1 dispatch\n    x: 0\n    y: 1\n    z: 1\n"
+        );
+        assert!(matches!(
+            recorder.dispatch_threads(8 * 65535 + 1, 1, 1),
+            Err(CommandError::DispatchTooLarge{ group_count: [65536, 1, 1], max_group_count: [65535; 3] })
+        ));
+
+        let info = ComputePipelineInfo::new(spirv.shader_info("main")).push_constant_size(40).workgroup_size([8, 0, 1]);
+        recorder.set_compute_pipeline(&device.create_compute_pipeline(&info).unwrap());
+        assert!(matches!(recorder.dispatch_threads(1, 1, 1), Err(CommandError::EmptyWorkgroupSize([8, 0, 1]))));
+    }
+
+    #[test]
+    fn destroys_owned_resources_deferred()
+    {
+        let device = device();
+        let buffer = device.create_buffer(&BufferInfo{ size: 64, allocate_info: MemoryFlags::empty(), name: "staging".into() }).unwrap();
+        let id = buffer.id();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        recorder.begin_capture();
+        buffer.destroy_deferred(&recorder).unwrap();
+        assert_eq!(
+            recorder.end_capture().unwrap().dump(),
+            format!("#0 destroy_buffer_deferred\n    id: buffer {:#x} \"staging\"\n", id.value)
+        );
+        // Destruction only happens once the submission finished and garbage was collected.
+        assert!(device.is_buffer_valid(id));
+    }
+
+    #[test]
+    fn rejects_mipmaps_of_unblittable_formats()
+    {
+        let device = device();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        let slice = ImageMipArraySlice{ base_mip_level: 0, level_count: 2, base_array_layer: 0, layer_count: 1 };
+        let generate = |format, filter| recorder.generate_mipmaps(image(&device, format, 8, 2), slice, filter);
+        for format in [Format::R32Uint, Format::R16Sint, Format::D32Sfloat, Format::Bc7UnormBlock] {
+            for filter in [Filter::NEAREST, Filter::LINEAR] {
+                assert!(matches!(generate(format, filter), Err(CommandError::UnsupportedBlitFormat(rejected)) if rejected == format));
+            }
+        }
+        assert!(matches!(generate(Format::R32Sfloat, Filter::LINEAR), Err(CommandError::UnsupportedBlitFormat(Format::R32Sfloat))));
+        generate(Format::R32Sfloat, Filter::NEAREST).unwrap();
+        generate(Format::R8g8b8a8Srgb, Filter::LINEAR).unwrap();
+    }
+
+    #[test]
+    fn rejects_mipmap_slices_outside_the_image()
+    {
+        let device = device();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        let image = image(&device, Format::R8g8b8a8Unorm, 8, 3);
+        let generate = |base_mip_level, level_count, base_array_layer, layer_count| recorder.generate_mipmaps(
+            image,
+            ImageMipArraySlice{ base_mip_level, level_count, base_array_layer, layer_count },
+            Filter::LINEAR,
+        );
+        for (base_mip_level, level_count, base_array_layer, layer_count) in [(0, 4, 0, 1), (3, 1, 0, 1), (1, 3, 0, 1), (0, 3, 1, 1), (0, 3, 0, 2), (u32::MAX, 2, 0, 1)] {
+            assert!(matches!(
+                generate(base_mip_level, level_count, base_array_layer, layer_count),
+                Err(CommandError::SliceOutOfRange{ mip_level_count: 3, array_layer_count: 1, .. })
+            ));
+        }
+        generate(0, 3, 0, 1).unwrap();
+        generate(1, 2, 0, 1).unwrap();
+    }
+
+    #[test]
+    fn blits_each_mip_into_the_next()
+    {
+        let device = device();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        let image = image(&device, Format::R8g8b8a8Unorm, 8, 3);
+        recorder.begin_capture();
+        recorder.generate_mipmaps(image, ImageMipArraySlice{ base_mip_level: 0, level_count: 3, base_array_layer: 0, layer_count: 1 }, Filter::LINEAR).unwrap();
+        let dump = recorder.end_capture().unwrap().dump();
+
+        let commands: Vec<_> = dump.lines().filter(|line| line.starts_with('#')).collect();
+        assert_eq!(commands, [
+            "#0 pipeline_barrier_image_transition",
+            "#1 blit_image_to_image",
+            "#2 pipeline_barrier_image_transition",
+            "#3 pipeline_barrier_image_transition",
+            "#4 blit_image_to_image",
+            "#5 pipeline_barrier_image_transition",
+            "#6 pipeline_barrier_image_transition",
+        ]);
+        for offsets in ["src_offsets[1]: (8, 8, 1)", "dst_offsets[1]: (4, 4, 1)", "src_offsets[1]: (4, 4, 1)", "dst_offsets[1]: (2, 2, 1)"] {
+            assert!(dump.contains(offsets), "missing `{}` in\n{}", offsets, dump);
+        }
+        assert!(dump.contains("filter: \"LINEAR\""));
+    }
+
+    #[test]
+    fn destroys_command_lists_that_are_never_submitted()
+    {
+        let device = device();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        let list = recorder.complete_current_commands().unwrap();
+        assert_eq!(live_command_lists(recorder.recorder), 1);
+        drop(list);
+        assert_eq!(live_command_lists(recorder.recorder), 0);
+
+        // The recorder keeps working, and a list may outlive it.
+        recorder.push_constants(&0u32);
+        let list = recorder.complete_current_commands().unwrap();
+        drop(recorder);
+        device.submit(&CommandSubmitInfo{
+            command_lists: std::slice::from_ref(&list),
+            ..Default::default()
+        }).unwrap();
+    }
+}
@@ -1,8 +1,11 @@
 use bitflags::bitflags;
 use std::mem;
 use std::sync;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
-use crate::{pipeline::RasterPipelineInfo, swapchain::SwapchainInfo, command_recorder::*, types::*};
+use crate::backend as daxa_sys;
+use crate::{pipeline::{CComputePipelineInfo, ComputePipelineInfo, PipelineError, RasterPipelineInfo}, shader::ShaderStage, swapchain::SwapchainInfo, command_recorder::*, types::*};
 
 #[repr(i32)]
 pub enum DeviceType {
@@ -61,7 +64,12 @@ impl Default for DeviceInfo
     }
 }
 
-pub struct Device(daxa_sys::daxa_Device);
+pub struct Device(pub(crate) daxa_sys::daxa_Device);
+
+// daxa synchronizes all device functions internally, so a device can be shared between threads
+// that each create and record their own command recorders.
+unsafe impl Send for Device {}
+unsafe impl Sync for Device {}
 
 macro_rules! device_create_fn {
     ($name:ident, $type:ident) => {
@@ -86,6 +94,34 @@ macro_rules! device_create_fn {
     };
 }
 
+macro_rules! device_resource_fns {
+    ($name:ident, $type:ident) => {
+        paste::item! {
+            pub fn [< destroy_ $name >] (&self, id: [< $type Id >]) -> std::result::Result<(), crate::types::Result> {
+                unsafe {
+                    match mem::transmute::<daxa_sys::daxa_Result, Result>(daxa_sys:: [< daxa_dvc_destroy_ $name >] (self.0, id)) {
+                        crate::Result::Success => Ok(()),
+                        error => Err(error),
+                    }
+                }
+            }
+
+            pub fn [< $name _info >] (&self, id: [< $type Id >]) -> std::result::Result<[< $type Info >], crate::types::Result> {
+                unsafe {
+                    let mut info = mem::zeroed::<daxa_sys:: [< daxa_ $type Info >]>();
+
+                    let c_result = daxa_sys:: [< daxa_dvc_info_ $name >] (self.0, id, &mut info);
+
+                    match mem::transmute::<daxa_sys::daxa_Result, Result>(c_result) {
+                        crate::Result::Success => Ok(mem::transmute(info)),
+                        error => Err(error),
+                    }
+                }
+            }
+        }
+    };
+}
+
 impl Device {
     pub fn buffer_memory_requirements(&self, info: &[BufferInfo]) -> MemoryRequirements {
         unsafe {
@@ -107,18 +143,78 @@ impl Device {
 
     //TODO: Patrick review these functions and make sure their signatures are correct. They seem to be correct (they all follow the same path/code), so using the same macro should be sound.
     // device_create_fn!(memory, MemoryBlock);
-    // device_create_fn!(image, Image);
-    // device_create_fn!(image_view, ImageView);
-    // device_create_fn!(sampler, Sampler);
-    // device_create_fn!(buffer, Buffer);
-    // device_create_fn!(raster_pipeline, RasterPipeline);
-    // device_create_fn!(compute_pipeline, ComputePipeline);
+    device_create_fn!(image, Image);
+    device_create_fn!(image_view, ImageView);
+    device_create_fn!(sampler, Sampler);
+    device_create_fn!(buffer, Buffer);
     // device_create_fn!(swapchain, Swapchain);
-    // device_create_fn!(binary_semaphore, BinarySemaphore);
-    // device_create_fn!(timeline_semaphore, TimelineSemaphore);
-    // device_create_fn!(event, Event);
-    // device_create_fn!(timeline_query_pool, TimelineQueryPool);
-    device_create_fn!(command_recorder, CommandRecorder);
+    device_create_fn!(binary_semaphore, BinarySemaphore);
+    device_create_fn!(timeline_semaphore, TimelineSemaphore);
+
+    device_resource_fns!(buffer, Buffer);
+    device_resource_fns!(image, Image);
+    device_resource_fns!(image_view, ImageView);
+    device_resource_fns!(sampler, Sampler);
+
+    pub fn create_command_recorder(
+        &self,
+        info: &CommandRecorderInfo,
+    ) -> std::result::Result<CommandRecorder, crate::types::Result> {
+        unsafe {
+            let mut recorder = mem::zeroed();
+
+            let c_result = daxa_sys::daxa_dvc_create_command_recorder(
+                self.0,
+                (info as *const CommandRecorderInfo).cast::<daxa_sys::daxa_CommandRecorderInfo>(),
+                &mut recorder,
+            );
+
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(c_result) {
+                crate::Result::Success => Ok(CommandRecorder::new(self.0, recorder)),
+                error => Err(error),
+            }
+        }
+    }
+
+    pub fn create_timeline_query_pool(
+        &self,
+        info: &TimelineQueryPoolInfo,
+    ) -> std::result::Result<TimelineQueryPool, crate::types::Result> {
+        unsafe {
+            let mut pool = mem::zeroed();
+
+            let c_result = daxa_sys::daxa_dvc_create_timeline_query_pool(
+                self.0,
+                (info as *const TimelineQueryPoolInfo).cast::<daxa_sys::daxa_TimelineQueryPoolInfo>(),
+                &mut pool,
+            );
+
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(c_result) {
+                crate::Result::Success => Ok(TimelineQueryPool {
+                    handle: pool,
+                    serial: next_serial(),
+                }),
+                error => Err(error),
+            }
+        }
+    }
+
+    pub fn create_event(&self, info: &EventInfo) -> std::result::Result<Event, crate::types::Result> {
+        unsafe {
+            let mut event = mem::zeroed();
+
+            let c_result = daxa_sys::daxa_dvc_create_event(
+                self.0,
+                (info as *const EventInfo).cast::<daxa_sys::daxa_EventInfo>(),
+                &mut event,
+            );
+
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(c_result) {
+                crate::Result::Success => Ok(Event::new(event)),
+                error => Err(error),
+            }
+        }
+    }
 
     pub fn is_buffer_valid(&self, buffer: BufferId) -> bool {
         unsafe { daxa_sys::daxa_dvc_is_buffer_valid(self.0, buffer) != 0 }
@@ -136,54 +232,61 @@ impl Device {
         unsafe { daxa_sys::daxa_dvc_is_sampler_valid(self.0, sampler) != 0 }
     }
 
-    // pub fn create_raster_pipeline(
-    //     &self,
-    //     info: &crate::pipeline::RasterPipelineInfo,
-    // ) -> std::result::Result<crate::pipeline::RasterPipeline, crate::types::Result> {
-    //     use crate::types::Result;
-    //     use Result::Success;
-    //     unsafe {
-    //         let c_info = info.as_ptr().cast::<daxa_sys::daxa_RasterPipelineInfo>();
-
-    //         let mut raster_pipeline = std::mem::zeroed();
-
-    //         let c_result = daxa_sys::daxa_dvc_create_raster_pipeline(
-    //             self.0,
-    //             c_info,
-    //             &mut raster_pipeline,
-    //         );
-
-    //         match mem::transmute::<daxa_sys::daxa_Result, Result>(c_result) {
-    //             Success => Ok(raster_pipeline),
-    //             error => Err(error),
-    //         }
-    //     }
-    // }
+    /// Fails with a [`PipelineError`] before calling into daxa when the info is inconsistent or
+    /// not supported by the device, when a stage's entry point is missing or when its push
+    /// constant block is larger than `push_constant_size`.
+    pub fn create_raster_pipeline(
+        &self,
+        info: &RasterPipelineInfo,
+    ) -> std::result::Result<RasterPipeline, PipelineError> {
+        info.validate(self.info().flags, self.properties().vendor_id)?;
+        unsafe {
+            let mut raster_pipeline = mem::zeroed();
 
-    // //compute
-    // pub fn create_compute_pipeline(
-    //     &self,
-    //     info: &crate::pipeline::ComputePipelineInfo,
-    // ) -> std::result::Result<crate::pipeline::ComputePipeline, crate::types::Result> {
-    //     use crate::types::Result;
-    //     use Result::Success;
-    //     unsafe {
-    //         let c_info = info.as_ptr().cast::<daxa_sys::daxa_ComputePipelineInfo>();
+            let c_result = daxa_sys::daxa_dvc_create_raster_pipeline(
+                self.0,
+                (info as *const RasterPipelineInfo).cast::<daxa_sys::daxa_RasterPipelineInfo>(),
+                &mut raster_pipeline,
+            );
 
-    //         let mut compute_pipeline = std::mem::zeroed();
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(c_result) {
+                Result::Success => Ok(RasterPipeline(raster_pipeline)),
+                error => Err(error.into()),
+            }
+        }
+    }
 
-    //         let c_result = daxa_sys::daxa_dvc_create_compute_pipeline(
-    //             self.0,
-    //             c_info,
-    //             &mut compute_pipeline,
-    //         );
+    /// Fails with a [`PipelineError`] before calling into daxa when the entry point is missing or
+    /// its push constant block is larger than `push_constant_size`.
+    pub fn create_compute_pipeline(
+        &self,
+        info: &ComputePipelineInfo,
+    ) -> std::result::Result<ComputePipeline, PipelineError> {
+        let entry_point = info.shader_info.validate(ShaderStage::Compute, info.push_constant_size)?;
+        let c_info = CComputePipelineInfo {
+            shader_info: Some(info.shader_info).into(),
+            push_constant_size: info.push_constant_size,
+            name: info.name,
+        };
+        unsafe {
+            let mut compute_pipeline = mem::zeroed();
 
-    //         match mem::transmute::<daxa_sys::daxa_Result, Result>(c_result) {
-    //             Success => Ok(compute_pipeline),
-    //             error => Err(error),
-    //         }
-    //     }
-    // }
+            let c_result = daxa_sys::daxa_dvc_create_compute_pipeline(
+                self.0,
+                (&c_info as *const CComputePipelineInfo).cast::<daxa_sys::daxa_ComputePipelineInfo>(),
+                &mut compute_pipeline,
+            );
+
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(c_result) {
+                Result::Success => Ok(ComputePipeline {
+                    handle: compute_pipeline,
+                    serial: next_serial(),
+                    workgroup_size: info.workgroup_size.or(entry_point.workgroup_size),
+                }),
+                error => Err(error.into()),
+            }
+        }
+    }
 
     // //swapchain
     // pub fn create_swapchain(
@@ -343,11 +446,93 @@ impl Device {
         }
     }
 
+    pub fn properties(&self) -> &DeviceProperties {
+        unsafe { daxa_sys::daxa_dvc_properties(self.0).as_ref().unwrap() }
+    }
+
     pub fn info(&self) -> &DeviceInfo {
         unsafe { mem::transmute::<_, _>(daxa_sys::daxa_dvc_info(self.0).as_ref().unwrap()) }
     }
 
-    //TODO submit
+    pub fn submit(&self, info: &CommandSubmitInfo) -> std::result::Result<(), CommandError> {
+        let event_states = validate_event_ops(info.command_lists)?;
+
+        let command_lists = info
+            .command_lists
+            .iter()
+            .map(|command_list| command_list.handle)
+            .collect::<Vec<_>>();
+        let c_timeline_pairs = |pairs: &[TimelinePair]| {
+            pairs
+                .iter()
+                .map(|pair| daxa_sys::daxa_TimelinePair {
+                    semaphore: pair.semaphore.0,
+                    value: pair.value,
+                })
+                .collect::<Vec<_>>()
+        };
+        let wait_timeline_semaphores = c_timeline_pairs(info.wait_timeline_semaphores);
+        let signal_timeline_semaphores = c_timeline_pairs(info.signal_timeline_semaphores);
+        unsafe {
+            let c_info = daxa_sys::daxa_CommandSubmitInfo {
+                wait_stages: info.wait_stages.bits(),
+                command_lists: command_lists.as_ptr(),
+                command_list_count: command_lists.len() as _,
+                wait_binary_semaphores: info.wait_binary_semaphores.as_ptr().cast(),
+                wait_binary_semaphore_count: info.wait_binary_semaphores.len() as _,
+                signal_binary_semaphores: info.signal_binary_semaphores.as_ptr().cast(),
+                signal_binary_semaphore_count: info.signal_binary_semaphores.len() as _,
+                wait_timeline_semaphores: wait_timeline_semaphores.as_ptr(),
+                wait_timeline_semaphore_count: wait_timeline_semaphores.len() as _,
+                signal_timeline_semaphores: signal_timeline_semaphores.as_ptr(),
+                signal_timeline_semaphore_count: signal_timeline_semaphores.len() as _,
+            };
+
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(daxa_sys::daxa_dvc_submit(
+                self.0, &c_info,
+            )) {
+                crate::Result::Success => {}
+                error => return Err(error.into()),
+            }
+        }
+
+        for (signaled, state) in event_states {
+            signaled.store(state, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    /// Records every job on its own thread into its own recorder, and returns the completed lists
+    /// in the order of `jobs`, so they can be passed to [`Device::submit`] together.
+    pub fn record_parallel<F>(
+        &self,
+        jobs: Vec<F>,
+    ) -> std::result::Result<Vec<ExecutableCommandList>, CommandError>
+    where
+        F: FnOnce(&CommandRecorder) + Send,
+    {
+        std::thread::scope(|scope| {
+            let threads = jobs
+                .into_iter()
+                .map(|job| {
+                    scope.spawn(move || {
+                        let recorder = self.create_command_recorder(&Default::default())?;
+                        job(&recorder);
+                        recorder.complete_current_commands()
+                    })
+                })
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .map(|thread| {
+                    thread
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        })
+    }
+
     //TODO present
 
     pub fn wait_idle(&self) -> std::result::Result<(), crate::types::Result> {
@@ -380,3 +565,100 @@ impl Device {
 //         }
 //     }
 // }
+
+// Replays the event operations of a submission in order, starting from what earlier submissions
+// left behind, and returns the state every touched event ends up in.
+fn validate_event_ops(
+    command_lists: &[ExecutableCommandList],
+) -> std::result::Result<Vec<(&Arc<AtomicBool>, bool)>, CommandError> {
+    let mut states: Vec<(&Arc<AtomicBool>, bool)> = Vec::new();
+    for (op, event) in command_lists.iter().flat_map(|command_list| &command_list.event_ops) {
+        let index = match states.iter().position(|(known, _)| Arc::ptr_eq(known, event)) {
+            Some(index) => index,
+            None => {
+                states.push((event, event.load(Ordering::Acquire)));
+                states.len() - 1
+            }
+        };
+        let signaled = &mut states[index].1;
+        match op {
+            EventOp::Signal => *signaled = true,
+            EventOp::Reset => *signaled = false,
+            EventOp::Wait if !*signaled => return Err(CommandError::EventWaitWithoutSignal),
+            EventOp::Wait => {}
+        }
+    }
+    Ok(states)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::null_backend::tests::device;
+
+    fn record(device: &Device, ops: impl FnOnce(&CommandRecorder)) -> ExecutableCommandList {
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        ops(&recorder);
+        recorder.complete_current_commands().unwrap()
+    }
+
+    #[test]
+    fn validates_event_ops_in_submission_order() {
+        let device = device();
+        let event = device.create_event(&Default::default()).unwrap();
+        let dependency = DependencyInfo::default();
+        let signal = record(&device, |recorder| recorder.signal_event(&event, &dependency));
+        let wait = record(&device, |recorder| recorder.wait_event(&event, &dependency));
+        let reset = record(&device, |recorder| recorder.reset_event(&event, PipelineStageFlags::empty()));
+
+        assert!(matches!(
+            validate_event_ops(std::slice::from_ref(&wait)),
+            Err(CommandError::EventWaitWithoutSignal)
+        ));
+        let lists = [signal, wait];
+        let states = validate_event_ops(&lists).unwrap();
+        assert_eq!(states.len(), 1);
+        assert!(states[0].1);
+        let [signal, wait] = lists;
+        assert!(matches!(
+            validate_event_ops(&[signal, reset, wait]),
+            Err(CommandError::EventWaitWithoutSignal)
+        ));
+    }
+
+    #[test]
+    fn submissions_remember_signaled_events() {
+        let device = device();
+        let event = device.create_event(&Default::default()).unwrap();
+        let dependency = DependencyInfo::default();
+        let wait = || record(&device, |recorder| recorder.wait_event(&event, &dependency));
+        let submit = |list: ExecutableCommandList| {
+            device.submit(&CommandSubmitInfo {
+                command_lists: std::slice::from_ref(&list),
+                ..Default::default()
+            })
+        };
+
+        assert!(matches!(submit(wait()), Err(CommandError::EventWaitWithoutSignal)));
+        submit(record(&device, |recorder| recorder.signal_event(&event, &dependency))).unwrap();
+        submit(wait()).unwrap();
+        submit(record(&device, |recorder| recorder.reset_event(&event, PipelineStageFlags::empty()))).unwrap();
+        assert!(matches!(submit(wait()), Err(CommandError::EventWaitWithoutSignal)));
+    }
+
+    #[test]
+    fn submits_a_command_list_more_than_once() {
+        let device = device();
+        let list = record(&device, |recorder| recorder.push_constants(&0u32));
+        let submit = || {
+            device.submit(&CommandSubmitInfo {
+                command_lists: std::slice::from_ref(&list),
+                ..Default::default()
+            })
+        };
+
+        submit().unwrap();
+        submit().unwrap();
+        assert_eq!(crate::null_backend::tests::submitted_command_lists(&device), 2);
+    }
+}
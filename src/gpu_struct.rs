@@ -0,0 +1,264 @@
+use std::path::Path;
+
+use crate::types::{BufferId, ImageId, ImageViewId, SamplerId};
+
+/// Derives [`GpuType`] for a `#[repr(C)]` struct whose fields are all [`GpuType`]s.
+///
+/// The field offsets and the size are checked at compile time against the scalar block layout
+/// daxa uses for buffer device addresses, or against std430 with `#[gpu(layout = "std430")]`.
+/// A struct that does not match fails to compile, naming the field that needs padding before it:
+///
+/// ```compile_fail
+/// # use daxa_rs::*;
+/// #[repr(C)]
+/// #[derive(Clone, Copy, GpuStruct)]
+/// #[gpu(layout = "std430")]
+/// struct Particle {
+///     position: Vector<f32, 3>,
+///     // std430 aligns vec3 to 16 bytes, so this field is at 16 in the shader but at 12 here.
+///     velocity: Vector<f32, 3>,
+/// }
+/// ```
+///
+/// The matching shader declaration, which works in GLSL and Slang, is emitted by [`GlslHeader`].
+pub use daxa_derive::GpuStruct;
+
+/// A type with a known layout in shaders. Implemented for scalars, [`Vector`]s, arrays, resource
+/// ids, and structs through `#[derive(GpuStruct)]`.
+///
+/// # Safety
+///
+/// The constants have to match the type's layout in shaders, structs containing the type check
+/// their own layout against them.
+pub unsafe trait GpuType: Copy + 'static {
+    const SCALAR_ALIGN: usize;
+    const SCALAR_SIZE: usize;
+    const STD430_ALIGN: usize;
+    const STD430_SIZE: usize;
+
+    /// The name of the type in shaders, using the `daxa_` types from `daxa/daxa.inl`.
+    fn glsl_name() -> String;
+
+    /// Declares a struct member or variable called `name` of this type.
+    fn glsl_declarator(name: &str) -> String {
+        format!("{} {}", Self::glsl_name(), name)
+    }
+
+    /// Adds the declarations this type needs to `header`, dependencies first.
+    fn glsl_declarations(_header: &mut GlslHeader) {}
+}
+
+/// The scalar types [`Vector`]s can be made of.
+///
+/// # Safety
+///
+/// See [`GpuType`].
+pub unsafe trait GpuScalar: GpuType {}
+
+macro_rules! gpu_scalar {
+    ($type:ty, $name:literal) => {
+        unsafe impl GpuType for $type {
+            const SCALAR_ALIGN: usize = std::mem::size_of::<$type>();
+            const SCALAR_SIZE: usize = std::mem::size_of::<$type>();
+            const STD430_ALIGN: usize = std::mem::size_of::<$type>();
+            const STD430_SIZE: usize = std::mem::size_of::<$type>();
+
+            fn glsl_name() -> String {
+                $name.to_owned()
+            }
+        }
+
+        unsafe impl GpuScalar for $type {}
+    };
+}
+
+gpu_scalar!(f32, "daxa_f32");
+gpu_scalar!(i32, "daxa_i32");
+gpu_scalar!(u32, "daxa_u32");
+gpu_scalar!(f64, "daxa_f64");
+gpu_scalar!(i64, "daxa_i64");
+// Also covers `BufferDeviceAddress`.
+gpu_scalar!(u64, "daxa_u64");
+
+macro_rules! gpu_id {
+    ($type:ty, $name:literal) => {
+        unsafe impl GpuType for $type {
+            const SCALAR_ALIGN: usize = 8;
+            const SCALAR_SIZE: usize = 8;
+            const STD430_ALIGN: usize = 8;
+            const STD430_SIZE: usize = 8;
+
+            fn glsl_name() -> String {
+                $name.to_owned()
+            }
+        }
+    };
+}
+
+gpu_id!(BufferId, "daxa_BufferId");
+gpu_id!(ImageId, "daxa_ImageId");
+gpu_id!(ImageViewId, "daxa_ImageViewId");
+gpu_id!(SamplerId, "daxa_SamplerId");
+
+/// A shader vector like `daxa_f32vec3`. Only 2, 3 and 4 components are [`GpuType`]s.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Vector<T, const N: usize>(pub [T; N]);
+
+macro_rules! gpu_vector {
+    ($len:literal, $std430_components:literal) => {
+        unsafe impl<T: GpuScalar> GpuType for Vector<T, $len> {
+            const SCALAR_ALIGN: usize = T::SCALAR_ALIGN;
+            const SCALAR_SIZE: usize = T::SCALAR_SIZE * $len;
+            // A vec3 is aligned like a vec4.
+            const STD430_ALIGN: usize = T::STD430_ALIGN * $std430_components;
+            const STD430_SIZE: usize = T::STD430_SIZE * $len;
+
+            fn glsl_name() -> String {
+                format!("{}vec{}", T::glsl_name(), $len)
+            }
+        }
+    };
+}
+
+gpu_vector!(2, 2);
+gpu_vector!(3, 4);
+gpu_vector!(4, 4);
+
+unsafe impl<T: GpuType, const N: usize> GpuType for [T; N] {
+    const SCALAR_ALIGN: usize = T::SCALAR_ALIGN;
+    const SCALAR_SIZE: usize = T::SCALAR_SIZE * N;
+    const STD430_ALIGN: usize = T::STD430_ALIGN;
+    const STD430_SIZE: usize = align_up(T::STD430_SIZE, T::STD430_ALIGN) * N;
+
+    fn glsl_name() -> String {
+        format!("{}[{}]", T::glsl_name(), N)
+    }
+
+    fn glsl_declarator(name: &str) -> String {
+        T::glsl_declarator(&format!("{}[{}]", name, N))
+    }
+
+    fn glsl_declarations(header: &mut GlslHeader) {
+        T::glsl_declarations(header);
+    }
+}
+
+const fn align_up(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+/// The member offsets, alignment and size of a struct with members of the given alignments and
+/// sizes. Used by `#[derive(GpuStruct)]`.
+#[doc(hidden)]
+pub const fn gpu_struct_layout<const N: usize>(members: [(usize, usize); N]) -> ([usize; N], usize, usize) {
+    let mut offsets = [0; N];
+    let mut offset = 0;
+    let mut struct_align = 1;
+    let mut index = 0;
+    while index < N {
+        let (align, size) = members[index];
+        offset = align_up(offset, align);
+        offsets[index] = offset;
+        offset += size;
+        if align > struct_align {
+            struct_align = align;
+        }
+        index += 1;
+    }
+    (offsets, struct_align, align_up(offset, struct_align))
+}
+
+/// A shader header with the declarations of [`GpuType`]s, to be included by the shaders that
+/// share them:
+///
+/// ```ignore
+/// GlslHeader::default()
+///     .declare::<Camera>()
+///     .declare::<Particle>()
+///     .write("shaders/shared.inl")?;
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct GlslHeader {
+    // Names and declarations, in the order they have to be declared in.
+    declarations: Vec<(String, String)>,
+}
+
+impl GlslHeader {
+    /// Declares `T` and every type it contains, each type only once.
+    pub fn declare<T: GpuType>(mut self) -> Self {
+        T::glsl_declarations(&mut self);
+        self
+    }
+
+    #[doc(hidden)]
+    pub fn add_declaration(&mut self, name: &str, declaration: String) {
+        if !self.declarations.iter().any(|(declared, _)| declared == name) {
+            self.declarations.push((name.to_owned(), declaration));
+        }
+    }
+
+    pub fn source(&self) -> String {
+        let mut source = "// Generated from Rust by daxa-rs, do not edit.\n#pragma once\n\n#include <daxa/daxa.inl>\n".to_owned();
+        for (_, declaration) in &self.declarations {
+            source.push('\n');
+            source += declaration;
+        }
+        source
+    }
+
+    /// Only writes the file when its content changed, so it does not trigger a rebuild in a
+    /// `PipelineManager` every time the application starts. Returns whether it did.
+    pub fn write(&self, path: impl AsRef<Path>) -> std::io::Result<bool> {
+        let source = self.source();
+        if std::fs::read_to_string(&path).is_ok_and(|existing| existing == source) {
+            return Ok(false);
+        }
+        std::fs::write(path, source)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, GpuStruct)]
+    struct Material {
+        albedo: Vector<f32, 3>,
+        texture: ImageViewId,
+        sampler: SamplerId,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, GpuStruct)]
+    #[gpu(layout = "std430")]
+    struct Particle {
+        position: Vector<f32, 3>,
+        mass: f32,
+        velocity: Vector<f32, 3>,
+        materials: u64,
+        neighbours: [u32; 2],
+        material: Material,
+    }
+
+    #[test]
+    fn computes_layouts() {
+        assert_eq!((Material::SCALAR_ALIGN, Material::SCALAR_SIZE), (8, 32));
+        assert_eq!((Material::STD430_ALIGN, Material::STD430_SIZE), (16, 32));
+        assert_eq!(<[Vector<f32, 3>; 2]>::STD430_SIZE, 32);
+        assert_eq!(gpu_struct_layout([(4, 4), (8, 8), (4, 12)]), ([0, 8, 16], 8, 32));
+    }
+
+    #[test]
+    fn declares_dependencies_first() {
+        let header = GlslHeader::default().declare::<Particle>().declare::<Material>();
+        assert_eq!(
+            header.source(),
+            "// Generated from Rust by daxa-rs, do not edit.\n#pragma once\n\n#include <daxa/daxa.inl>\n\
+             \nstruct Material\n{\n    daxa_f32vec3 albedo;\n    daxa_ImageViewId texture;\n    daxa_SamplerId sampler;\n};\nDAXA_DECL_BUFFER_PTR(Material)\n\
+             \nstruct Particle\n{\n    daxa_f32vec3 position;\n    daxa_f32 mass;\n    daxa_f32vec3 velocity;\n    daxa_u64 materials;\n    daxa_u32 neighbours[2];\n    Material material;\n};\nDAXA_DECL_BUFFER_PTR(Particle)\n"
+        );
+    }
+}
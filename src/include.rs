@@ -0,0 +1,274 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use crate::shader::*;
+
+/// Where `#include`s are looked up. Virtual files shadow files on disk with the same path, so
+/// generated headers and `include_str!` sources can be included like any other file.
+///
+/// Paths are normalized before they are compared, so `./a.glsl` and `a.glsl` name the same file.
+#[derive(Clone, Default, Debug)]
+pub struct ShaderFileSystem {
+    search_paths: Vec<PathBuf>,
+    virtual_files: HashMap<PathBuf, String>,
+}
+
+impl ShaderFileSystem {
+    /// Search paths are tried in the order they were added.
+    pub fn search_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.search_paths.push(path.into());
+        self
+    }
+
+    pub fn virtual_file(mut self, path: impl Into<PathBuf>, source: impl Into<String>) -> Self {
+        self.set_virtual_file(path, source);
+        self
+    }
+
+    pub fn set_virtual_file(&mut self, path: impl Into<PathBuf>, source: impl Into<String>) {
+        self.virtual_files.insert(normalize(&path.into()), source.into());
+    }
+
+    pub fn read(&self, path: &Path) -> std::io::Result<String> {
+        let path = normalize(path);
+        match self.virtual_files.get(&path) {
+            Some(source) => Ok(source.clone()),
+            None => std::fs::read_to_string(path),
+        }
+    }
+
+    // Expects a normalized path.
+    fn exists(&self, path: &Path) -> bool {
+        self.virtual_files.contains_key(path) || path.is_file()
+    }
+
+    /// `#include "file"` is looked up next to the including file first, `#include <file>` only in
+    /// the search paths. The returned path is normalized.
+    pub fn resolve(&self, include: &str, includer: &Path, quoted: bool) -> std::option::Option<PathBuf> {
+        let relative = includer.parent().filter(|_| quoted).map(|directory| directory.join(include));
+        relative
+            .into_iter()
+            .chain(self.search_paths.iter().map(|directory| directory.join(include)))
+            .map(|path| normalize(&path))
+            .find(|path| self.exists(path))
+    }
+
+    /// Replaces every `#include` in `path` with the included file, recursively. A file containing
+    /// `#pragma once` is only included the first time.
+    ///
+    /// Only includes are handled, every other directive is left to the compiler. That includes
+    /// `#if` and `#ifdef`: an `#include` inside a disabled block is still expanded, and fails if
+    /// the file does not exist.
+    pub fn preprocess(&self, path: &Path) -> std::result::Result<PreprocessedSource, ShaderError> {
+        let mut preprocessor = Preprocessor {
+            file_system: self,
+            output: PreprocessedSource {
+                source: String::new(),
+                files: Vec::new(),
+                segments: Vec::new(),
+            },
+            line: 1,
+            stack: Vec::new(),
+            included_once: HashSet::new(),
+        };
+        preprocessor.file(&normalize(path))?;
+        Ok(preprocessor.output)
+    }
+}
+
+/// Removes `.` components and folds `..` into the directory before it. Works on the path alone,
+/// without following symlinks, so it is only meant for comparing paths.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+// Output lines starting at `line` come from `path`, starting at `source_line`.
+#[derive(Clone, Debug)]
+struct Segment {
+    line: u32,
+    path: PathBuf,
+    source_line: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct PreprocessedSource {
+    pub source: String,
+    /// Every file that ended up in `source`, normalized, the preprocessed file first.
+    pub files: Vec<PathBuf>,
+    segments: Vec<Segment>,
+}
+
+impl PreprocessedSource {
+    /// The file and line a line of `source` came from.
+    pub fn source_location(&self, line: u32) -> (&Path, u32) {
+        let segment = self
+            .segments
+            .iter()
+            .rev()
+            .find(|segment| segment.line <= line)
+            .unwrap_or(&self.segments[0]);
+        (&segment.path, segment.source_line + line.saturating_sub(segment.line))
+    }
+
+    /// Points a message about `source` at the file it came from.
+    pub fn map_message(&self, message: CompileMessage) -> CompileMessage {
+        if message.line == 0 {
+            return message;
+        }
+        let (path, line) = self.source_location(message.line);
+        CompileMessage {
+            path: path.to_owned(),
+            line,
+            ..message
+        }
+    }
+}
+
+struct Preprocessor<'a> {
+    file_system: &'a ShaderFileSystem,
+    output: PreprocessedSource,
+    line: u32,
+    stack: Vec<PathBuf>,
+    included_once: HashSet<PathBuf>,
+}
+
+impl<'a> Preprocessor<'a> {
+    fn file(&mut self, path: &Path) -> std::result::Result<(), ShaderError> {
+        let source = self.file_system.read(path)?;
+        if source.lines().any(is_pragma_once) && !self.included_once.insert(path.to_owned()) {
+            return Ok(());
+        }
+        if !self.output.files.iter().any(|file| file == path) {
+            self.output.files.push(path.to_owned());
+        }
+        self.stack.push(path.to_owned());
+        self.segment(path, 1);
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index as u32 + 1;
+            let Some((include, quoted)) = parse_include(line) else {
+                // `#pragma once` lines are kept empty, so the line count stays the same.
+                if !is_pragma_once(line) {
+                    self.output.source.push_str(line);
+                }
+                self.output.source.push('\n');
+                self.line += 1;
+                continue;
+            };
+
+            let error = |message: String| {
+                ShaderError::Compile(vec![CompileMessage {
+                    path: path.to_owned(),
+                    line: line_number,
+                    column: 1,
+                    message,
+                }])
+            };
+            let included = self
+                .file_system
+                .resolve(include, path, quoted)
+                .ok_or_else(|| error(format!("can not find include `{}`", include)))?;
+            // A `#pragma once` file including itself again is skipped by `file` like any repeat.
+            if self.stack.contains(&included) && !self.included_once.contains(&included) {
+                return Err(error(format!("`{}` includes itself", included.display())));
+            }
+            self.file(&included)?;
+            self.segment(path, line_number + 1);
+        }
+
+        self.stack.pop();
+        Ok(())
+    }
+
+    fn segment(&mut self, path: &Path, source_line: u32) {
+        self.output.segments.push(Segment {
+            line: self.line,
+            path: path.to_owned(),
+            source_line,
+        });
+    }
+}
+
+// Returns the included path and whether it was quoted rather than in angle brackets.
+fn parse_include(line: &str) -> std::option::Option<(&str, bool)> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start().strip_prefix("include")?.trim();
+    if let Some(rest) = rest.strip_prefix('"') {
+        Some((rest.split_once('"')?.0, true))
+    } else {
+        Some((rest.strip_prefix('<')?.split_once('>')?.0, false))
+    }
+}
+
+fn is_pragma_once(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix('#')
+        .is_some_and(|rest| rest.split_whitespace().eq(["pragma", "once"]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_system() -> ShaderFileSystem {
+        ShaderFileSystem::default()
+            .search_path("include")
+            .virtual_file("shaders/main.glsl", "#include \"common.glsl\"\n#include <shared.glsl>\nvoid main() {}")
+            .virtual_file("shaders/common.glsl", "#pragma once\n#include <shared.glsl>\nfloat common;")
+            .virtual_file("include/shared.glsl", "#pragma once\nfloat shared;")
+    }
+
+    #[test]
+    fn includes_files_once() {
+        let preprocessed = file_system().preprocess(Path::new("shaders/main.glsl")).unwrap();
+        assert_eq!(preprocessed.source, "\n\nfloat shared;\nfloat common;\nvoid main() {}\n");
+        assert_eq!(
+            preprocessed.files,
+            [Path::new("shaders/main.glsl"), Path::new("shaders/common.glsl"), Path::new("include/shared.glsl")]
+        );
+        assert_eq!(preprocessed.source_location(3), (Path::new("include/shared.glsl"), 2));
+        assert_eq!(preprocessed.source_location(4), (Path::new("shaders/common.glsl"), 3));
+        assert_eq!(preprocessed.source_location(5), (Path::new("shaders/main.glsl"), 3));
+    }
+
+    #[test]
+    fn reports_missing_and_recursive_includes() {
+        let file_system = file_system()
+            .virtual_file("a.glsl", "\n#include \"missing.glsl\"")
+            .virtual_file("b.glsl", "#include \"b.glsl\"");
+        let Err(ShaderError::Compile(messages)) = file_system.preprocess(Path::new("a.glsl")) else {
+            panic!("expected a missing include");
+        };
+        assert_eq!((messages[0].path.as_path(), messages[0].line), (Path::new("a.glsl"), 2));
+        assert!(matches!(file_system.preprocess(Path::new("b.glsl")), Err(ShaderError::Compile(_))));
+    }
+
+    #[test]
+    fn skips_pragma_once_files_including_themselves() {
+        let file_system = file_system().virtual_file("once.glsl", "#pragma once\n#include \"once.glsl\"\nfloat once;");
+        let preprocessed = file_system.preprocess(Path::new("once.glsl")).unwrap();
+        assert_eq!(preprocessed.source, "\nfloat once;\n");
+        assert_eq!(preprocessed.source_location(2), (Path::new("once.glsl"), 3));
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        assert_eq!(normalize(Path::new("./shaders/../include/./shared.glsl")), Path::new("include/shared.glsl"));
+        assert_eq!(normalize(Path::new("../shaders/a.glsl")), Path::new("../shaders/a.glsl"));
+
+        let file_system = ShaderFileSystem::default()
+            .virtual_file("./shaders/main.glsl", "#include \"../include/shared.glsl\"")
+            .virtual_file("include/shared.glsl", "float shared;");
+        let preprocessed = file_system.preprocess(Path::new("shaders/./main.glsl")).unwrap();
+        assert_eq!(preprocessed.files, [Path::new("shaders/main.glsl"), Path::new("include/shared.glsl")]);
+    }
+}
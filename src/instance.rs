@@ -3,7 +3,7 @@ use crate::device::*;
 
 use crate::types::*;
 use bitflags::bitflags;
-use daxa_sys;
+use crate::backend as daxa_sys;
 
 pub struct Instance {
     instance: daxa_sys::daxa_Instance,
@@ -57,15 +57,20 @@ impl Instance {
     }
 
     pub fn create_device(self: &Self, device_info: &DeviceInfo) -> std::result::Result<Device, crate::Result> {
-        unsafe
-        {
-            let device : daxa_sys::daxa_Device = std::mem::zeroed();
-            let daxa_result = daxa_sys::daxa_instance_create_device(
-                std::mem::transmute(self.instance),
-                std::mem::transmute(device_info),
-                std::mem::transmute(device)
+        use crate::types::Result;
+        unsafe {
+            let mut device = std::mem::zeroed();
+
+            let c_result = daxa_sys::daxa_instance_create_device(
+                self.instance,
+                (device_info as *const DeviceInfo).cast::<daxa_sys::daxa_DeviceInfo>(),
+                &mut device,
             );
-            Err(std::mem::transmute(daxa_result))
+
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(c_result) {
+                Result::Success => Ok(Device(device)),
+                error => Err(error),
+            }
         }
     }
 
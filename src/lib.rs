@@ -1,10 +1,41 @@
+// Lets `#[derive(GpuStruct)]` refer to `::daxa_rs` inside of this crate, too.
+extern crate self as daxa_rs;
+
+mod backend;
+mod capture;
 mod device;
+mod gpu_struct;
+mod include;
 mod instance;
+#[cfg(any(test, feature = "null-backend"))]
+mod null_backend;
 mod pipeline;
+#[cfg(feature = "naga")]
+mod pipeline_manager;
+mod profiler;
+mod reflection;
+mod shader;
+#[cfg(feature = "naga")]
+mod shader_compiler;
 mod types;
 mod swapchain;
 mod command_recorder;
+mod tracked_recorder;
+pub use capture::*;
+pub use command_recorder::*;
+pub use device::*;
+pub use gpu_struct::*;
+pub use include::*;
 pub use instance::*;
+pub use pipeline::*;
+#[cfg(feature = "naga")]
+pub use pipeline_manager::*;
+pub use profiler::*;
+pub use reflection::*;
+pub use shader::*;
+#[cfg(feature = "naga")]
+pub use shader_compiler::*;
+pub use tracked_recorder::*;
 pub use types::*;
 
 #[cfg(test)]
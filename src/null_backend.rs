@@ -0,0 +1,754 @@
+//! A stand-in for the daxa C library that never touches a gpu.
+//!
+//! It hands out ids and handles, remembers the infos resources were created with, accepts every
+//! recorded command and submission, and completes timeline semaphores and queries immediately.
+//!
+//! Types and constants are glob imported from `daxa_sys`, functions defined here shadow theirs.
+//! Every function the crate calls has to be defined here, otherwise it would silently reach the
+//! real library; `tests::overrides_every_called_function` checks that. Functions the null backend
+//! can not emulate, like everything involving a window, fail.
+#![allow(unused_variables, clippy::missing_safety_doc)]
+
+use std::os::raw::c_void;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub use daxa_sys::*;
+
+const SUCCESS: daxa_Result = daxa_Result_DAXA_RESULT_SUCCESS;
+// Invalid ids and unsupported functions, daxa's more specific codes describe driver state the null
+// backend does not have.
+const FAILURE: daxa_Result = daxa_Result_DAXA_RESULT_ERROR_UNKNOWN;
+
+struct NullInstance {
+    info: daxa_InstanceInfo,
+}
+
+struct NullBuffer {
+    info: daxa_BufferInfo,
+    memory: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Resources {
+    buffers: Vec<Option<NullBuffer>>,
+    images: Vec<Option<daxa_ImageInfo>>,
+    image_views: Vec<Option<daxa_ImageViewInfo>>,
+    samplers: Vec<Option<daxa_SamplerInfo>>,
+}
+
+// Ids are slot index + 1, slots are never reused, so stale ids stay invalid.
+fn insert<T>(slots: &mut Vec<Option<T>>, value: T) -> u64 {
+    slots.push(Some(value));
+    slots.len() as u64
+}
+
+fn get<T>(slots: &[Option<T>], id: u64) -> Option<&T> {
+    slots.get((id as usize).wrapping_sub(1))?.as_ref()
+}
+
+fn get_mut<T>(slots: &mut [Option<T>], id: u64) -> Option<&mut T> {
+    slots.get_mut((id as usize).wrapping_sub(1))?.as_mut()
+}
+
+fn remove<T>(slots: &mut [Option<T>], id: u64) -> daxa_Result {
+    match slots.get_mut((id as usize).wrapping_sub(1)).and_then(Option::take) {
+        Some(_) => SUCCESS,
+        None => FAILURE,
+    }
+}
+
+struct NullDevice {
+    info: daxa_DeviceInfo,
+    properties: daxa_DeviceProperties,
+    resources: Mutex<Resources>,
+    submitted_command_lists: AtomicU64,
+}
+
+struct NullCommandRecorder {
+    commands: Vec<&'static str>,
+    // Completed lists that have not been destroyed yet, shared with those lists.
+    live_lists: Arc<AtomicU64>,
+}
+
+struct NullExecutableCommandList {
+    commands: Vec<&'static str>,
+    live_lists: Arc<AtomicU64>,
+}
+
+impl Drop for NullExecutableCommandList {
+    fn drop(&mut self) {
+        self.live_lists.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// Boxes of zero sized types all share one dangling address, so objects without state carry a
+// serial number to get handles of their own, like the real library's.
+static NEXT_SERIAL: AtomicU64 = AtomicU64::new(1);
+
+fn serial() -> u64 {
+    NEXT_SERIAL.fetch_add(1, Ordering::Relaxed)
+}
+
+struct NullPipeline {
+    _serial: u64,
+}
+
+struct NullTimelineQueryPool {
+    _serial: u64,
+}
+
+struct NullEvent {
+    _serial: u64,
+}
+
+struct NullBinarySemaphore {
+    _serial: u64,
+}
+
+struct NullTimelineSemaphore {
+    value: AtomicU64,
+}
+
+fn into_handle<T, H>(value: T) -> *mut H {
+    Box::into_raw(Box::new(value)).cast()
+}
+
+unsafe fn from_handle<'a, T, H>(handle: *mut H) -> &'a mut T {
+    &mut *handle.cast::<T>()
+}
+
+unsafe fn destroy_handle<T, H>(handle: *mut H) -> u64 {
+    drop(Box::from_raw(handle.cast::<T>()));
+    0
+}
+
+unsafe fn resources<'a>(device: daxa_Device) -> std::sync::MutexGuard<'a, Resources> {
+    from_handle::<NullDevice, _>(device).resources.lock().unwrap()
+}
+
+pub unsafe extern "C" fn daxa_create_instance(
+    info: *const daxa_InstanceInfo,
+    out_instance: *mut daxa_Instance,
+) -> daxa_Result {
+    *out_instance = into_handle(NullInstance { info: *info });
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_instance_info(instance: daxa_Instance) -> *const daxa_InstanceInfo {
+    &from_handle::<NullInstance, _>(instance).info
+}
+
+pub unsafe extern "C" fn daxa_default_device_score(properties: *const daxa_DeviceProperties) -> i32 {
+    1
+}
+
+pub unsafe extern "C" fn daxa_default_format_selector(format: VkFormat) -> i32 {
+    1
+}
+
+pub unsafe extern "C" fn daxa_instance_create_device(
+    instance: daxa_Instance,
+    info: *const daxa_DeviceInfo,
+    out_device: *mut daxa_Device,
+) -> daxa_Result {
+    let mut properties = std::mem::zeroed::<daxa_DeviceProperties>();
+    properties.limits.timestampPeriod = 1.0;
+    properties.limits.minUniformBufferOffsetAlignment = 256;
+    properties.limits.maxUniformBufferRange = 65536;
+    properties.limits.maxComputeWorkGroupCount = [65535; 3];
+    *out_device = into_handle(NullDevice {
+        info: *info,
+        properties,
+        resources: Default::default(),
+        submitted_command_lists: AtomicU64::new(0),
+    });
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_info(device: daxa_Device) -> *const daxa_DeviceInfo {
+    &from_handle::<NullDevice, _>(device).info
+}
+
+pub unsafe extern "C" fn daxa_dvc_properties(device: daxa_Device) -> *const daxa_DeviceProperties {
+    &from_handle::<NullDevice, _>(device).properties
+}
+
+pub unsafe extern "C" fn daxa_dvc_buffer_memory_requirements(
+    device: daxa_Device,
+    info: *const daxa_BufferInfo,
+) -> VkMemoryRequirements {
+    VkMemoryRequirements {
+        size: (*info).size as _,
+        alignment: 256,
+        memoryTypeBits: !0,
+    }
+}
+
+pub unsafe extern "C" fn daxa_dvc_image_memory_requirements(
+    device: daxa_Device,
+    info: *const daxa_ImageInfo,
+) -> VkMemoryRequirements {
+    let info = &*info;
+    VkMemoryRequirements {
+        // Generous upper bound, four 32 bit channels per texel.
+        size: info.size.width as u64
+            * info.size.height.max(1) as u64
+            * info.size.depth.max(1) as u64
+            * info.array_layer_count.max(1) as u64
+            * 16
+            * 2,
+        alignment: 256,
+        memoryTypeBits: !0,
+    }
+}
+
+pub unsafe extern "C" fn daxa_dvc_create_buffer(
+    device: daxa_Device,
+    info: *const daxa_BufferInfo,
+    out_id: *mut daxa_BufferId,
+) -> daxa_Result {
+    let info = *info;
+    let value = insert(
+        &mut resources(device).buffers,
+        NullBuffer {
+            info,
+            memory: vec![0; info.size as usize],
+        },
+    );
+    *out_id = daxa_BufferId { value };
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_create_image(
+    device: daxa_Device,
+    info: *const daxa_ImageInfo,
+    out_id: *mut daxa_ImageId,
+) -> daxa_Result {
+    let value = insert(&mut resources(device).images, *info);
+    *out_id = daxa_ImageId { value };
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_create_image_view(
+    device: daxa_Device,
+    info: *const daxa_ImageViewInfo,
+    out_id: *mut daxa_ImageViewId,
+) -> daxa_Result {
+    let value = insert(&mut resources(device).image_views, *info);
+    *out_id = daxa_ImageViewId { value };
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_create_sampler(
+    device: daxa_Device,
+    info: *const daxa_SamplerInfo,
+    out_id: *mut daxa_SamplerId,
+) -> daxa_Result {
+    let value = insert(&mut resources(device).samplers, *info);
+    *out_id = daxa_SamplerId { value };
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_destroy_buffer(device: daxa_Device, id: daxa_BufferId) -> daxa_Result {
+    remove(&mut resources(device).buffers, id.value)
+}
+
+pub unsafe extern "C" fn daxa_dvc_destroy_image(device: daxa_Device, id: daxa_ImageId) -> daxa_Result {
+    remove(&mut resources(device).images, id.value)
+}
+
+pub unsafe extern "C" fn daxa_dvc_destroy_image_view(device: daxa_Device, id: daxa_ImageViewId) -> daxa_Result {
+    remove(&mut resources(device).image_views, id.value)
+}
+
+pub unsafe extern "C" fn daxa_dvc_destroy_sampler(device: daxa_Device, id: daxa_SamplerId) -> daxa_Result {
+    remove(&mut resources(device).samplers, id.value)
+}
+
+pub unsafe extern "C" fn daxa_dvc_info_buffer(
+    device: daxa_Device,
+    id: daxa_BufferId,
+    out_info: *mut daxa_BufferInfo,
+) -> daxa_Result {
+    match get(&resources(device).buffers, id.value) {
+        Some(buffer) => {
+            *out_info = buffer.info;
+            SUCCESS
+        }
+        None => FAILURE,
+    }
+}
+
+pub unsafe extern "C" fn daxa_dvc_info_image(
+    device: daxa_Device,
+    id: daxa_ImageId,
+    out_info: *mut daxa_ImageInfo,
+) -> daxa_Result {
+    match get(&resources(device).images, id.value) {
+        Some(info) => {
+            *out_info = *info;
+            SUCCESS
+        }
+        None => FAILURE,
+    }
+}
+
+pub unsafe extern "C" fn daxa_dvc_info_image_view(
+    device: daxa_Device,
+    id: daxa_ImageViewId,
+    out_info: *mut daxa_ImageViewInfo,
+) -> daxa_Result {
+    match get(&resources(device).image_views, id.value) {
+        Some(info) => {
+            *out_info = *info;
+            SUCCESS
+        }
+        None => FAILURE,
+    }
+}
+
+pub unsafe extern "C" fn daxa_dvc_info_sampler(
+    device: daxa_Device,
+    id: daxa_SamplerId,
+    out_info: *mut daxa_SamplerInfo,
+) -> daxa_Result {
+    match get(&resources(device).samplers, id.value) {
+        Some(info) => {
+            *out_info = *info;
+            SUCCESS
+        }
+        None => FAILURE,
+    }
+}
+
+pub unsafe extern "C" fn daxa_dvc_is_buffer_valid(device: daxa_Device, id: daxa_BufferId) -> daxa_Bool8 {
+    get(&resources(device).buffers, id.value).is_some() as _
+}
+
+pub unsafe extern "C" fn daxa_dvc_is_image_valid(device: daxa_Device, id: daxa_ImageId) -> daxa_Bool8 {
+    get(&resources(device).images, id.value).is_some() as _
+}
+
+pub unsafe extern "C" fn daxa_dvc_is_image_view_valid(device: daxa_Device, id: daxa_ImageViewId) -> daxa_Bool8 {
+    get(&resources(device).image_views, id.value).is_some() as _
+}
+
+pub unsafe extern "C" fn daxa_dvc_is_sampler_valid(device: daxa_Device, id: daxa_SamplerId) -> daxa_Bool8 {
+    get(&resources(device).samplers, id.value).is_some() as _
+}
+
+pub unsafe extern "C" fn daxa_dvc_buffer_device_address(
+    device: daxa_Device,
+    id: daxa_BufferId,
+    out_address: *mut daxa_BufferDeviceAddress,
+) -> daxa_Result {
+    if get(&resources(device).buffers, id.value).is_none() {
+        return FAILURE;
+    }
+    // Distinct, never dereferenced, addresses per buffer.
+    *out_address = id.value << 32;
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_buffer_host_address(
+    device: daxa_Device,
+    id: daxa_BufferId,
+    out_address: *mut *mut c_void,
+) -> daxa_Result {
+    match get_mut(&mut resources(device).buffers, id.value) {
+        Some(buffer) => {
+            // The vector is never resized, so the pointer stays valid until the buffer is destroyed.
+            *out_address = buffer.memory.as_mut_ptr().cast();
+            SUCCESS
+        }
+        None => FAILURE,
+    }
+}
+
+pub unsafe extern "C" fn daxa_dvc_create_command_recorder(
+    device: daxa_Device,
+    info: *const daxa_CommandRecorderInfo,
+    out_recorder: *mut daxa_CommandRecorder,
+) -> daxa_Result {
+    *out_recorder = into_handle(NullCommandRecorder {
+        commands: Vec::new(),
+        live_lists: Arc::default(),
+    });
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_create_timeline_query_pool(
+    device: daxa_Device,
+    info: *const daxa_TimelineQueryPoolInfo,
+    out_pool: *mut daxa_TimelineQueryPool,
+) -> daxa_Result {
+    *out_pool = into_handle(NullTimelineQueryPool { _serial: serial() });
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_create_event(
+    device: daxa_Device,
+    info: *const daxa_EventInfo,
+    out_event: *mut daxa_Event,
+) -> daxa_Result {
+    *out_event = into_handle(NullEvent { _serial: serial() });
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_create_binary_semaphore(
+    device: daxa_Device,
+    info: *const daxa_BinarySemaphoreInfo,
+    out_semaphore: *mut daxa_BinarySemaphore,
+) -> daxa_Result {
+    *out_semaphore = into_handle(NullBinarySemaphore { _serial: serial() });
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_create_timeline_semaphore(
+    device: daxa_Device,
+    info: *const daxa_TimelineSemaphoreInfo,
+    out_semaphore: *mut daxa_TimelineSemaphore,
+) -> daxa_Result {
+    *out_semaphore = into_handle(NullTimelineSemaphore {
+        value: AtomicU64::new((*info).initial_value),
+    });
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_create_raster_pipeline(
+    device: daxa_Device,
+    info: *const daxa_RasterPipelineInfo,
+    out_pipeline: *mut daxa_RasterPipeline,
+) -> daxa_Result {
+    *out_pipeline = into_handle(NullPipeline { _serial: serial() });
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_create_compute_pipeline(
+    device: daxa_Device,
+    info: *const daxa_ComputePipelineInfo,
+    out_pipeline: *mut daxa_ComputePipeline,
+) -> daxa_Result {
+    *out_pipeline = into_handle(NullPipeline { _serial: serial() });
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_submit(device: daxa_Device, info: *const daxa_CommandSubmitInfo) -> daxa_Result {
+    let info = &*info;
+    from_handle::<NullDevice, _>(device)
+        .submitted_command_lists
+        .fetch_add(info.command_list_count as u64, Ordering::Relaxed);
+    // Nothing to execute, so everything the submission signals is reached right away.
+    if info.signal_timeline_semaphore_count != 0 {
+        let signals = std::slice::from_raw_parts(
+            info.signal_timeline_semaphores,
+            info.signal_timeline_semaphore_count as usize,
+        );
+        for signal in signals {
+            from_handle::<NullTimelineSemaphore, _>(signal.semaphore)
+                .value
+                .fetch_max(signal.value, Ordering::AcqRel);
+        }
+    }
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_wait_idle(device: daxa_Device) -> daxa_Result {
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_dvc_collect_garbage(device: daxa_Device) -> daxa_Result {
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_timeline_query_pool_query_results(
+    pool: daxa_TimelineQueryPool,
+    start_index: u32,
+    count: u32,
+    out_results: *mut u64,
+) -> daxa_Result {
+    // Every query is available and reads as tick zero.
+    for query in 0..count as usize {
+        *out_results.add(query * 2) = 0;
+        *out_results.add(query * 2 + 1) = 1;
+    }
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_timeline_semaphore_get_value(
+    semaphore: daxa_TimelineSemaphore,
+    out_value: *mut u64,
+) -> daxa_Result {
+    *out_value = from_handle::<NullTimelineSemaphore, _>(semaphore).value.load(Ordering::Acquire);
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_timeline_semaphore_wait_for_value(
+    semaphore: daxa_TimelineSemaphore,
+    value: u64,
+    timeout: u64,
+) -> daxa_Result {
+    // Submissions complete immediately, a value that is not reached yet never will be.
+    match from_handle::<NullTimelineSemaphore, _>(semaphore).value.load(Ordering::Acquire) >= value {
+        true => SUCCESS,
+        false => daxa_Result_DAXA_RESULT_TIMEOUT,
+    }
+}
+
+// There is no window system, so there are no swapchains. The swapchain functions only exist so a
+// swapchain can never reach the real library.
+pub unsafe extern "C" fn daxa_dvc_create_swapchain(
+    device: daxa_Device,
+    info: *const daxa_SwapchainInfo,
+    out_swapchain: *mut daxa_Swapchain,
+) -> daxa_Result {
+    FAILURE
+}
+
+pub unsafe extern "C" fn daxa_swp_acquire_next_image(
+    swapchain: daxa_Swapchain,
+    out_image_id: *mut daxa_ImageId,
+) -> daxa_Result {
+    FAILURE
+}
+
+pub unsafe extern "C" fn daxa_swp_set_present_mode(
+    swapchain: daxa_Swapchain,
+    present_mode: VkPresentModeKHR,
+) -> daxa_Result {
+    FAILURE
+}
+
+pub unsafe extern "C" fn daxa_swp_resize(swapchain: daxa_Swapchain) -> daxa_Result {
+    FAILURE
+}
+
+pub unsafe extern "C" fn daxa_timeline_query_pool_dec_refcnt(pool: daxa_TimelineQueryPool) -> u64 {
+    destroy_handle::<NullTimelineQueryPool, _>(pool)
+}
+
+pub unsafe extern "C" fn daxa_raster_pipeline_dec_refcnt(pipeline: daxa_RasterPipeline) -> u64 {
+    destroy_handle::<NullPipeline, _>(pipeline)
+}
+
+pub unsafe extern "C" fn daxa_compute_pipeline_dec_refcnt(pipeline: daxa_ComputePipeline) -> u64 {
+    destroy_handle::<NullPipeline, _>(pipeline)
+}
+
+pub unsafe extern "C" fn daxa_event_dec_refcnt(event: daxa_Event) -> u64 {
+    destroy_handle::<NullEvent, _>(event)
+}
+
+pub unsafe extern "C" fn daxa_binary_semaphore_dec_refcnt(semaphore: daxa_BinarySemaphore) -> u64 {
+    destroy_handle::<NullBinarySemaphore, _>(semaphore)
+}
+
+pub unsafe extern "C" fn daxa_timeline_semaphore_dec_refcnt(semaphore: daxa_TimelineSemaphore) -> u64 {
+    destroy_handle::<NullTimelineSemaphore, _>(semaphore)
+}
+
+pub unsafe extern "C" fn daxa_destroy_command_recorder(recorder: daxa_CommandRecorder) {
+    destroy_handle::<NullCommandRecorder, _>(recorder);
+}
+
+pub unsafe extern "C" fn daxa_cmd_complete_current_commands(
+    recorder: daxa_CommandRecorder,
+    out_executable_commands: *mut daxa_ExecutableCommandList,
+) -> daxa_Result {
+    let recorder = from_handle::<NullCommandRecorder, _>(recorder);
+    recorder.live_lists.fetch_add(1, Ordering::Relaxed);
+    *out_executable_commands = into_handle(NullExecutableCommandList {
+        commands: std::mem::take(&mut recorder.commands),
+        live_lists: recorder.live_lists.clone(),
+    });
+    SUCCESS
+}
+
+pub unsafe extern "C" fn daxa_destroy_executable_command_list(executable_commands: daxa_ExecutableCommandList) {
+    destroy_handle::<NullExecutableCommandList, _>(executable_commands);
+}
+
+unsafe fn record(recorder: daxa_CommandRecorder, command: &'static str) {
+    from_handle::<NullCommandRecorder, _>(recorder).commands.push(command);
+}
+
+macro_rules! null_commands {
+    ($($name:ident($($arg:ident: $arg_type:ty),*) $(-> $result:ty)?;)*) => {
+        $(
+            pub unsafe extern "C" fn $name(recorder: daxa_CommandRecorder, $($arg: $arg_type),*) $(-> $result)? {
+                record(recorder, stringify!($name));
+                $({
+                    let result: $result = SUCCESS;
+                    result
+                })?
+            }
+        )*
+    };
+}
+
+null_commands! {
+    daxa_cmd_copy_buffer_to_buffer(info: *const daxa_BufferCopyInfo) -> daxa_Result;
+    daxa_cmd_copy_buffer_to_image(info: *const daxa_BufferImageCopyInfo) -> daxa_Result;
+    daxa_cmd_copy_image_to_buffer(info: *const daxa_ImageBufferCopyInfo) -> daxa_Result;
+    daxa_cmd_copy_image_to_image(info: *const daxa_ImageCopyInfo) -> daxa_Result;
+    daxa_cmd_blit_image_to_image(info: *const daxa_ImageBlitInfo) -> daxa_Result;
+    daxa_cmd_clear_buffer(info: *const daxa_BufferClearInfo) -> daxa_Result;
+    daxa_cmd_clear_image(info: *const daxa_ImageClearInfo) -> daxa_Result;
+    daxa_cmd_pipeline_barrier(info: *const daxa_MemoryBarrierInfo);
+    daxa_cmd_pipeline_barrier_image_transition(info: *const daxa_ImageMemoryBarrierInfo) -> daxa_Result;
+    daxa_cmd_signal_event(info: *const daxa_EventSignalInfo);
+    daxa_cmd_wait_events(infos: *const daxa_EventWaitInfo, count: usize);
+    daxa_cmd_reset_event(info: *const daxa_ResetEventInfo);
+    daxa_cmd_push_constant(data: *const c_void, size: u32);
+    daxa_cmd_set_uniform_buffer(info: *const daxa_SetUniformBufferInfo) -> daxa_Result;
+    daxa_cmd_set_compute_pipeline(pipeline: daxa_ComputePipeline);
+    daxa_cmd_dispatch(info: *const daxa_DispatchInfo);
+    daxa_cmd_dispatch_indirect(info: *const daxa_DispatchIndirectInfo) -> daxa_Result;
+    daxa_cmd_draw_indirect(info: *const daxa_DrawIndirectInfo) -> daxa_Result;
+    daxa_cmd_draw_mesh_tasks_indirect(info: *const daxa_DrawMeshTasksIndirectInfo) -> daxa_Result;
+    daxa_cmd_begin_renderpass(info: *const daxa_RenderPassBeginInfo) -> daxa_Result;
+    daxa_cmd_end_renderpass();
+    daxa_cmd_begin_label(info: *const daxa_CommandLabelInfo);
+    daxa_cmd_end_label();
+    daxa_cmd_write_timestamp(info: *const daxa_WriteTimestampInfo);
+    daxa_cmd_reset_timestamps(info: *const daxa_ResetTimestampsInfo);
+    daxa_cmd_destroy_buffer_deferred(id: daxa_BufferId) -> daxa_Result;
+    daxa_cmd_destroy_image_deferred(id: daxa_ImageId) -> daxa_Result;
+    daxa_cmd_destroy_image_view_deferred(id: daxa_ImageViewId) -> daxa_Result;
+    daxa_cmd_destroy_sampler_deferred(id: daxa_SamplerId) -> daxa_Result;
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::Path;
+
+    use crate::*;
+
+    pub(crate) fn device() -> Device {
+        let instance = Instance::new(&InstanceInfo::default()).unwrap();
+        instance.create_device(&Default::default()).unwrap()
+    }
+
+    /// A 2D image with a single layer.
+    pub(crate) fn image(device: &Device, format: Format, size: u32, mip_level_count: u32) -> ImageId {
+        device
+            .create_image(&ImageInfo {
+                flags: ImageCreateFlags::empty(),
+                dimensions: 2,
+                format,
+                size: Extent3D {
+                    width: size,
+                    height: size,
+                    depth: 1,
+                },
+                mip_level_count,
+                array_layer_count: 1,
+                sample_count: 1,
+                usage: ImageUsageFlags::empty(),
+                allocate_info: MemoryFlags::empty(),
+                name: "image".into(),
+            })
+            .unwrap()
+            .id()
+    }
+
+    pub(crate) fn submitted_command_lists(device: &Device) -> u64 {
+        unsafe { super::from_handle::<super::NullDevice, _>(device.0) }
+            .submitted_command_lists
+            .load(super::Ordering::Relaxed)
+    }
+
+    /// Command lists completed by the recorder that have not been destroyed yet.
+    pub(crate) fn live_command_lists(recorder: crate::backend::daxa_CommandRecorder) -> u64 {
+        unsafe { super::from_handle::<super::NullCommandRecorder, _>(recorder) }
+            .live_lists
+            .load(super::Ordering::Relaxed)
+    }
+
+    #[test]
+    fn overrides_every_called_function() {
+        let source_directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let null_backend = std::fs::read_to_string(source_directory.join("null_backend.rs")).unwrap();
+        for entry in std::fs::read_dir(&source_directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.ends_with("null_backend.rs") {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            // Names pasted together by macros are not checked here, they are covered by the tests
+            // that call them.
+            for line in source.lines().filter(|line| !line.trim_start().starts_with("//")) {
+                for call in line.split("daxa_sys::").skip(1) {
+                    let name: String = call.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+                    if name.is_empty() || !call[name.len()..].starts_with('(') {
+                        continue;
+                    }
+                    let defined = null_backend.contains(&format!("fn {}(", name))
+                        || null_backend.contains(&format!("    {}(", name));
+                    assert!(
+                        defined,
+                        "`{}` called in {} is not defined by the null backend",
+                        name,
+                        path.display()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn allocates_and_destroys_ids() {
+        let device = device();
+        let buffer = device
+            .create_buffer(&BufferInfo {
+                size: 64,
+                allocate_info: MemoryFlags::empty(),
+                name: "buffer".into(),
+            })
+            .unwrap()
+            .id();
+        assert!(device.is_buffer_valid(buffer));
+        assert_eq!(device.buffer_info(buffer).unwrap().size, 64);
+
+        device.destroy_buffer(buffer).unwrap();
+        assert!(!device.is_buffer_valid(buffer));
+        assert!(matches!(device.destroy_buffer(buffer), Err(Result::ErrorUnknown)));
+        assert!(matches!(device.buffer_info(buffer), Err(Result::ErrorUnknown)));
+    }
+
+    #[test]
+    fn hands_out_distinct_handles() {
+        let device = device();
+        let first = device.create_event(&Default::default()).unwrap();
+        let second = device.create_event(&Default::default()).unwrap();
+        assert_ne!(first.handle, second.handle);
+    }
+
+    #[test]
+    fn submits_and_waits_on_a_timeline() {
+        let device = device();
+        let timeline = device
+            .create_timeline_semaphore(&TimelineSemaphoreInfo {
+                initial_value: 0,
+                name: "timeline".into(),
+            })
+            .unwrap();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        let commands = recorder.complete_current_commands().unwrap();
+
+        device
+            .submit(&CommandSubmitInfo {
+                command_lists: std::slice::from_ref(&commands),
+                signal_timeline_semaphores: &[TimelinePair {
+                    semaphore: &timeline,
+                    value: 1,
+                }],
+                ..Default::default()
+            })
+            .unwrap();
+        timeline.wait_for_value(1, u64::MAX).unwrap();
+        assert_eq!(timeline.value().unwrap(), 1);
+        assert!(matches!(timeline.wait_for_value(2, 0), Err(Result::Timeout)));
+    }
+}
@@ -1,21 +1,206 @@
 use bitflags::bitflags;
 
-use crate::{types, CompareOp, Format};
+use std::marker::PhantomData;
+use std::mem;
 
+use crate::backend as daxa_sys;
+use crate::reflection::{self, EntryPoint};
+use crate::{types, CompareOp, DeviceFlags, Format, ShaderError, ShaderStage, SpirV};
+
+const NVIDIA_VENDOR_ID: u32 = 0x10de;
+pub(crate) const MAX_COLOR_ATTACHMENTS: usize = 8;
+
+#[derive(Debug)]
+pub enum PipelineError {
+    Daxa(types::Result),
+    /// The byte code of a stage could not be reflected.
+    Shader(ShaderError),
+    /// A stage's `entry_point` names a function the byte code does not declare as an entry point.
+    MissingEntryPoint(String),
+    /// A stage's entry point is declared for another stage, `found` is `None` for execution
+    /// models daxa has no pipeline stage for.
+    WrongStage {
+        entry_point: String,
+        expected: ShaderStage,
+        found: std::option::Option<ShaderStage>,
+    },
+    /// The info's `push_constant_size` is smaller than the push constant block a stage declares.
+    PushConstantSizeTooSmall { push_constant_size: u32, declared: u32 },
+    /// A raster pipeline has both a mesh and a vertex shader.
+    MeshAndVertexShader,
+    /// Only one of the tesselation control and evaluation shaders is set.
+    IncompleteTesselation,
+    /// Tesselation shaders need the `PatchList` primitive topology.
+    TesselationWithoutPatchList(PrimitiveTopology),
+    /// `conservative_raster_info` is set, but the device was created without
+    /// `DeviceFlags::CONSERVATIVE_RASTERIZATION`.
+    ConservativeRasterizationDisabled,
+    /// A color attachment blends with one of the `*Ext` ops, daxa does not enable the advanced
+    /// blend extension they need.
+    AdvancedBlendOp { attachment: usize, blend_op: BlendOp },
+    /// `PolygonMode::FillRectangleNV` is only supported by NVIDIA devices.
+    FillRectangleUnsupported { vendor_id: u32 },
+    /// A color attachment has a blend state but an undefined format.
+    BlendWithoutFormat { attachment: usize },
+    /// A raster pipeline has more than 8 color attachments.
+    TooManyColorAttachments(usize),
+    /// A pipeline name or entry point is longer than `SmallString::MAX_LEN` bytes.
+    NameTooLong(String),
+}
+
+impl std::fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineError::Daxa(result) => write!(f, "daxa returned {:?}", result),
+            PipelineError::Shader(error) => write!(f, "invalid shader: {}", error),
+            PipelineError::MissingEntryPoint(name) => write!(f, "the shader has no entry point named `{}`", name),
+            PipelineError::WrongStage { entry_point, expected, found: Some(found) } => write!(
+                f,
+                "the entry point `{}` is a {:?} shader, but is used as a {:?} shader",
+                entry_point, found, expected
+            ),
+            PipelineError::WrongStage { entry_point, expected, found: None } => write!(
+                f,
+                "the entry point `{}` is not a shader stage daxa supports, but is used as a {:?} shader",
+                entry_point, expected
+            ),
+            PipelineError::PushConstantSizeTooSmall { push_constant_size, declared } => write!(
+                f,
+                "push_constant_size is {} bytes, but a stage declares a push constant block of {} bytes",
+                push_constant_size, declared
+            ),
+            PipelineError::MeshAndVertexShader => {
+                write!(f, "a raster pipeline can not have both a mesh and a vertex shader")
+            }
+            PipelineError::IncompleteTesselation => {
+                write!(f, "tesselation needs both a control and an evaluation shader")
+            }
+            PipelineError::TesselationWithoutPatchList(topology) => {
+                write!(f, "tesselation needs the PatchList primitive topology, not {:?}", topology)
+            }
+            PipelineError::ConservativeRasterizationDisabled => {
+                write!(f, "conservative rasterization is not enabled on the device")
+            }
+            PipelineError::AdvancedBlendOp { attachment, blend_op } => write!(
+                f,
+                "color attachment {} uses the advanced blend op {:?}, which daxa does not enable",
+                attachment, blend_op
+            ),
+            PipelineError::FillRectangleUnsupported { vendor_id } => write!(
+                f,
+                "PolygonMode::FillRectangleNV needs an NVIDIA device, the device's vendor id is {:#06x}",
+                vendor_id
+            ),
+            PipelineError::BlendWithoutFormat { attachment } => {
+                write!(f, "color attachment {} blends but has an undefined format", attachment)
+            }
+            PipelineError::TooManyColorAttachments(count) => write!(
+                f,
+                "{} color attachments, a raster pipeline has at most {}",
+                count, MAX_COLOR_ATTACHMENTS
+            ),
+            PipelineError::NameTooLong(name) => {
+                write!(f, "`{}` is longer than {} bytes", name, types::SmallString::MAX_LEN)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {
+    fn source(&self) -> std::option::Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PipelineError::Shader(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<types::Result> for PipelineError {
+    fn from(result: types::Result) -> Self {
+        PipelineError::Daxa(result)
+    }
+}
+
+impl From<ShaderError> for PipelineError {
+    fn from(error: ShaderError) -> Self {
+        PipelineError::Shader(error)
+    }
+}
+
+// Pipeline sources come from data files, so their strings are checked before they are turned
+// into `SmallString`s, a name that does not fit is an error there rather than silently truncated.
+pub(crate) fn check_name(name: &str) -> std::result::Result<(), PipelineError> {
+    if name.len() > types::SmallString::MAX_LEN {
+        return Err(PipelineError::NameTooLong(name.to_owned()));
+    }
+    Ok(())
+}
+
+/// One stage of a pipeline, borrowing the byte code until the pipeline is created.
 #[repr(C)]
-pub struct ShaderInfo {
+#[derive(Clone, Copy)]
+pub struct ShaderInfo<'a> {
     byte_code: *const u32,
     byte_code_size: usize,
     entry_point: types::SmallString,
+    _byte_code: PhantomData<&'a SpirV>,
+}
+
+impl<'a> ShaderInfo<'a> {
+    /// `entry_point` is the name of the function the stage starts in.
+    /// Names longer than [`types::SmallString::MAX_LEN`] bytes are truncated.
+    pub fn new(spirv: &'a SpirV, entry_point: &str) -> Self {
+        Self {
+            byte_code: spirv.words().as_ptr(),
+            byte_code_size: spirv.words().len(),
+            entry_point: types::SmallString::truncated(entry_point),
+            _byte_code: PhantomData,
+        }
+    }
+
+    fn byte_code(&self) -> &'a [u32] {
+        // Borrowed from a `SpirV` for 'a.
+        unsafe { std::slice::from_raw_parts(self.byte_code, self.byte_code_size) }
+    }
+
+    /// Reflects the byte code, checking that the entry point exists, is declared for `stage` and
+    /// that its push constant block fits into `push_constant_size`.
+    pub(crate) fn validate(
+        &self,
+        stage: ShaderStage,
+        push_constant_size: u32,
+    ) -> std::result::Result<EntryPoint, PipelineError> {
+        let reflection = reflection::reflect(self.byte_code())?;
+        if reflection.push_constant_size > push_constant_size {
+            return Err(PipelineError::PushConstantSizeTooSmall {
+                push_constant_size,
+                declared: reflection.push_constant_size,
+            });
+        }
+        let name = self.entry_point.as_str();
+        let entry_point = reflection
+            .entry_point(name)
+            .ok_or_else(|| PipelineError::MissingEntryPoint(name.to_owned()))?;
+        if entry_point.stage != Some(stage) {
+            return Err(PipelineError::WrongStage {
+                entry_point: name.to_owned(),
+                expected: stage,
+                found: entry_point.stage,
+            });
+        }
+        Ok(entry_point.clone())
+    }
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct DepthTestInfo {
-    depth_attachment_format: types::Format,
-    enable_depth_write: bool,
-    depth_test_compare_op: CompareOp,
-    min_depth_bounds: f32,
-    max_depth_bounds: f32,
+    pub depth_attachment_format: types::Format,
+    pub enable_depth_write: bool,
+    pub depth_test_compare_op: CompareOp,
+    pub min_depth_bounds: f32,
+    pub max_depth_bounds: f32,
 }
 
 impl Default for DepthTestInfo {
@@ -32,11 +217,22 @@ impl Default for DepthTestInfo {
 
 #[repr(C)]
 pub struct RenderAttachment {
-    format: types::Format,
-    blend: types::Option<BlendInfo>,
+    pub format: types::Format,
+    pub blend: types::Option<BlendInfo>,
+}
+
+impl Default for RenderAttachment {
+    fn default() -> Self {
+        Self {
+            format: Format::Undefined,
+            blend: None.into(),
+        }
+    }
 }
 
 #[repr(i32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BlendFactor {
     Zero = daxa_sys::VkBlendFactor_VK_BLEND_FACTOR_ZERO,
     One = daxa_sys::VkBlendFactor_VK_BLEND_FACTOR_ONE,
@@ -60,6 +256,8 @@ pub enum BlendFactor {
 }
 
 #[repr(i32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BlendOp {
     Add = daxa_sys::VkBlendOp_VK_BLEND_OP_ADD,
     Subtract = daxa_sys::VkBlendOp_VK_BLEND_OP_SUBTRACT,
@@ -114,6 +312,42 @@ pub enum BlendOp {
     BlueExt = daxa_sys::VkBlendOp_VK_BLEND_OP_BLUE_EXT,
 }
 
+impl BlendOp {
+    /// Whether this is one of the `*Ext` ops from `VK_EXT_blend_operation_advanced`.
+    pub fn is_advanced(self) -> bool {
+        !matches!(
+            self,
+            BlendOp::Add | BlendOp::Subtract | BlendOp::ReverseSubtract | BlendOp::Min | BlendOp::Max
+        )
+    }
+}
+
+// Flags are (de)serialized as lists of flag names like `["R", "G"]`, unknown names are rejected.
+#[cfg(feature = "serde")]
+macro_rules! serde_flags {
+    ($flags:ident, [$($flag:ident),*]) => {
+        impl serde::Serialize for $flags {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+                let flags = [$((stringify!($flag), $flags::$flag)),*];
+                serializer.collect_seq(flags.into_iter().filter(|&(_, flag)| self.contains(flag)).map(|(name, _)| name))
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $flags {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+                let mut flags = $flags::empty();
+                for name in Vec::<String>::deserialize(deserializer)? {
+                    flags |= match name.as_str() {
+                        $(stringify!($flag) => $flags::$flag,)*
+                        _ => return Err(serde::de::Error::unknown_variant(&name, &[$(stringify!($flag)),*])),
+                    };
+                }
+                Ok(flags)
+            }
+        }
+    };
+}
+
 bitflags! {
     #[derive(Default)]
     pub struct ColorComponentFlags: i32 {
@@ -125,14 +359,16 @@ bitflags! {
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct BlendInfo {
-    src_color_blend_factor: BlendFactor,
-    dst_color_blend_factor: BlendFactor,
-    color_blend_op: BlendOp,
-    src_alpha_blend_factor: BlendFactor,
-    dst_alpha_blend_factor: BlendFactor,
-    alpha_blend_op: BlendOp,
-    color_write_mask: ColorComponentFlags,
+    pub src_color_blend_factor: BlendFactor,
+    pub dst_color_blend_factor: BlendFactor,
+    pub color_blend_op: BlendOp,
+    pub src_alpha_blend_factor: BlendFactor,
+    pub dst_alpha_blend_factor: BlendFactor,
+    pub alpha_blend_op: BlendOp,
+    pub color_write_mask: ColorComponentFlags,
 }
 
 impl Default for BlendInfo {
@@ -153,18 +389,24 @@ impl Default for BlendInfo {
 }
 
 #[repr(i32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TesselationDomainOrigin {
     UpperLeft = daxa_sys::VkTessellationDomainOrigin_VK_TESSELLATION_DOMAIN_ORIGIN_UPPER_LEFT,
     LowerLeft = daxa_sys::VkTessellationDomainOrigin_VK_TESSELLATION_DOMAIN_ORIGIN_LOWER_LEFT,
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TesselationInfo {
-    control_points: u32,
-    origin: TesselationDomainOrigin,
+    pub control_points: u32,
+    pub origin: TesselationDomainOrigin,
 }
 
 #[repr(i32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PrimitiveTopology {
     PointList = daxa_sys::VkPrimitiveTopology_VK_PRIMITIVE_TOPOLOGY_POINT_LIST,
     LineList = daxa_sys::VkPrimitiveTopology_VK_PRIMITIVE_TOPOLOGY_LINE_LIST,
@@ -184,6 +426,8 @@ pub enum PrimitiveTopology {
 }
 
 #[repr(i32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PolygonMode {
     Fill = daxa_sys::VkPolygonMode_VK_POLYGON_MODE_FILL,
     Line = daxa_sys::VkPolygonMode_VK_POLYGON_MODE_LINE,
@@ -191,6 +435,9 @@ pub enum PolygonMode {
     FillRectangleNV = daxa_sys::VkPolygonMode_VK_POLYGON_MODE_FILL_RECTANGLE_NV,
 }
 
+#[cfg(feature = "serde")]
+serde_flags!(ColorComponentFlags, [R, G, B, A]);
+
 bitflags! {
     #[derive(Default)]
     pub struct CullModeFlags: i32 {
@@ -201,14 +448,21 @@ bitflags! {
     }
 }
 
+// `NONE` is an empty list and `FRONT_AND_BACK` lists both bits.
+#[cfg(feature = "serde")]
+serde_flags!(CullModeFlags, [FRONT_BIT, BACK_BIT]);
+
 #[repr(i32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FrontFace {
     CounterClockwise = daxa_sys::VkFrontFace_VK_FRONT_FACE_COUNTER_CLOCKWISE,
     Clockwise = daxa_sys::VkFrontFace_VK_FRONT_FACE_CLOCKWISE,
 }
 
 #[repr(i32)]
-#[derive(Default)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ConsevativeRasterizationModeEXT {
     #[default]
     DisabledExt = daxa_sys::VkConservativeRasterizationModeEXT_VK_CONSERVATIVE_RASTERIZATION_MODE_DISABLED_EXT,
@@ -217,27 +471,30 @@ pub enum ConsevativeRasterizationModeEXT {
 }
 
 #[repr(C)]
-#[derive(Default)]
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct ConsevativeRasterInfo {
-    mode: ConsevativeRasterizationModeEXT,
-    size: f32,
+    pub mode: ConsevativeRasterizationModeEXT,
+    pub size: f32,
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
 pub struct RasterizerInfo {
-    primitive_topology: PrimitiveTopology,
-    primitive_restart_enable: bool,
-    polygon_mode: PolygonMode,
-    face_culling: CullModeFlags,
-    front_face_winding: FrontFace,
-    depth_clamp_enable: bool,
-    rasterizer_discard_enable: bool,
-    depth_bias_enable: bool,
-    depth_bias_constant_factor: f32,
-    depth_bias_clamp: f32,
-    depth_bias_slope_factor: f32,
-    line_width: f32,
-    conservative_raster_info: types::Option<ConsevativeRasterInfo>,
+    pub primitive_topology: PrimitiveTopology,
+    pub primitive_restart_enable: bool,
+    pub polygon_mode: PolygonMode,
+    pub face_culling: CullModeFlags,
+    pub front_face_winding: FrontFace,
+    pub depth_clamp_enable: bool,
+    pub rasterizer_discard_enable: bool,
+    pub depth_bias_enable: bool,
+    pub depth_bias_constant_factor: f32,
+    pub depth_bias_clamp: f32,
+    pub depth_bias_slope_factor: f32,
+    pub line_width: f32,
+    pub conservative_raster_info: types::Option<ConsevativeRasterInfo>,
 }
 
 impl Default for RasterizerInfo {
@@ -261,14 +518,15 @@ impl Default for RasterizerInfo {
 }
 
 #[repr(C)]
-pub struct RasterPipelineInfo {
-    mesh_shader_info: types::Option<ShaderInfo>,
-    vertex_shader_info: types::Option<ShaderInfo>,
-    tesselation_control_shader_info: types::Option<ShaderInfo>,
-    tesselation_evaluation_shader_info: types::Option<ShaderInfo>,
-    fragment_shader_info: types::Option<ShaderInfo>,
-    task_shader_info: types::Option<ShaderInfo>,
-    color_attachments: [RenderAttachment; 8],
+pub struct RasterPipelineInfo<'a> {
+    mesh_shader_info: types::Option<ShaderInfo<'a>>,
+    vertex_shader_info: types::Option<ShaderInfo<'a>>,
+    tesselation_control_shader_info: types::Option<ShaderInfo<'a>>,
+    tesselation_evaluation_shader_info: types::Option<ShaderInfo<'a>>,
+    fragment_shader_info: types::Option<ShaderInfo<'a>>,
+    task_shader_info: types::Option<ShaderInfo<'a>>,
+    color_attachments: [RenderAttachment; MAX_COLOR_ATTACHMENTS],
+    color_attachments_count: u64,
     depth_test: types::Option<DepthTestInfo>,
     tesselation: types::Option<TesselationInfo>,
     raster: RasterizerInfo,
@@ -276,9 +534,420 @@ pub struct RasterPipelineInfo {
     name: types::SmallString,
 }
 
+impl<'a> Default for RasterPipelineInfo<'a> {
+    fn default() -> Self {
+        Self {
+            mesh_shader_info: None.into(),
+            vertex_shader_info: None.into(),
+            tesselation_control_shader_info: None.into(),
+            tesselation_evaluation_shader_info: None.into(),
+            fragment_shader_info: None.into(),
+            task_shader_info: None.into(),
+            color_attachments: Default::default(),
+            color_attachments_count: 0,
+            depth_test: None.into(),
+            tesselation: None.into(),
+            raster: Default::default(),
+            push_constant_size: 0,
+            name: Default::default(),
+        }
+    }
+}
+
+// The info is passed to daxa as is, see `Device::create_raster_pipeline`.
+const _: () = assert!(mem::size_of::<RasterPipelineInfo>() == mem::size_of::<daxa_sys::daxa_RasterPipelineInfo>());
+const _: () = assert!(mem::align_of::<RasterPipelineInfo>() == mem::align_of::<daxa_sys::daxa_RasterPipelineInfo>());
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, mesh_shader_info) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, mesh_shader_info));
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, vertex_shader_info) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, vertex_shader_info));
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, tesselation_control_shader_info) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, tesselation_control_shader_info));
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, tesselation_evaluation_shader_info) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, tesselation_evaluation_shader_info));
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, fragment_shader_info) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, fragment_shader_info));
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, task_shader_info) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, task_shader_info));
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, color_attachments) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, color_attachments.data));
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, color_attachments_count) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, color_attachments.size));
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, depth_test) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, depth_test));
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, tesselation) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, tesselation));
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, raster) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, raster));
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, push_constant_size) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, push_constant_size));
+const _: () = assert!(mem::offset_of!(RasterPipelineInfo, name) == mem::offset_of!(daxa_sys::daxa_RasterPipelineInfo, name));
+
+/// Fluent setters, starting from [`RasterPipelineInfo::default`]:
+///
+/// ```ignore
+/// let info = RasterPipelineInfo::default()
+///     .vertex_shader(vertex)
+///     .fragment_shader(fragment)
+///     .color_attachment(Format::B8g8r8a8Srgb, None)
+///     .depth_test(DepthTestInfo { enable_depth_write: true, ..Default::default() })
+///     .name("triangle");
+/// ```
+impl<'a> RasterPipelineInfo<'a> {
+    pub fn mesh_shader(mut self, shader: ShaderInfo<'a>) -> Self {
+        self.mesh_shader_info = Some(shader).into();
+        self
+    }
+
+    pub fn vertex_shader(mut self, shader: ShaderInfo<'a>) -> Self {
+        self.vertex_shader_info = Some(shader).into();
+        self
+    }
+
+    pub fn tesselation_control_shader(mut self, shader: ShaderInfo<'a>) -> Self {
+        self.tesselation_control_shader_info = Some(shader).into();
+        self
+    }
+
+    pub fn tesselation_evaluation_shader(mut self, shader: ShaderInfo<'a>) -> Self {
+        self.tesselation_evaluation_shader_info = Some(shader).into();
+        self
+    }
+
+    pub fn fragment_shader(mut self, shader: ShaderInfo<'a>) -> Self {
+        self.fragment_shader_info = Some(shader).into();
+        self
+    }
+
+    pub fn task_shader(mut self, shader: ShaderInfo<'a>) -> Self {
+        self.task_shader_info = Some(shader).into();
+        self
+    }
+
+    /// Appends a color attachment. Panics when there are already 8.
+    pub fn color_attachment(mut self, format: Format, blend: std::option::Option<BlendInfo>) -> Self {
+        let index = self.color_attachments_count as usize;
+        assert!(index < MAX_COLOR_ATTACHMENTS, "a raster pipeline has at most 8 color attachments");
+        self.color_attachments[index] = RenderAttachment {
+            format,
+            blend: blend.into(),
+        };
+        self.color_attachments_count += 1;
+        self
+    }
+
+    pub fn depth_test(mut self, depth_test: DepthTestInfo) -> Self {
+        self.depth_test = Some(depth_test).into();
+        self
+    }
+
+    pub fn tesselation(mut self, tesselation: TesselationInfo) -> Self {
+        self.tesselation = Some(tesselation).into();
+        self
+    }
+
+    pub fn raster(mut self, raster: RasterizerInfo) -> Self {
+        self.raster = raster;
+        self
+    }
+
+    pub fn push_constant_size(mut self, push_constant_size: u32) -> Self {
+        self.push_constant_size = push_constant_size;
+        self
+    }
+
+    /// Names longer than [`types::SmallString::MAX_LEN`] bytes are truncated.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = types::SmallString::truncated(name);
+        self
+    }
+
+    /// Checks the info against itself and the device before anything is passed to daxa.
+    pub(crate) fn validate(&self, device_flags: DeviceFlags, vendor_id: u32) -> std::result::Result<(), PipelineError> {
+        let tesselation_control = self.tesselation_control_shader_info.as_ref().is_some();
+        let tesselation_evaluation = self.tesselation_evaluation_shader_info.as_ref().is_some();
+        if self.mesh_shader_info.as_ref().is_some() && self.vertex_shader_info.as_ref().is_some() {
+            return Err(PipelineError::MeshAndVertexShader);
+        }
+        if tesselation_control != tesselation_evaluation {
+            return Err(PipelineError::IncompleteTesselation);
+        }
+        if tesselation_control && self.raster.primitive_topology != PrimitiveTopology::PatchList {
+            return Err(PipelineError::TesselationWithoutPatchList(self.raster.primitive_topology));
+        }
+        if self.raster.conservative_raster_info.as_ref().is_some()
+            && !device_flags.contains(DeviceFlags::CONSERVATIVE_RASTERIZATION)
+        {
+            return Err(PipelineError::ConservativeRasterizationDisabled);
+        }
+        if self.raster.polygon_mode == PolygonMode::FillRectangleNV && vendor_id != NVIDIA_VENDOR_ID {
+            return Err(PipelineError::FillRectangleUnsupported { vendor_id });
+        }
+        let color_attachments = &self.color_attachments[..self.color_attachments_count as usize];
+        for (attachment, color_attachment) in color_attachments.iter().enumerate() {
+            let Some(blend) = color_attachment.blend.as_ref() else {
+                continue;
+            };
+            if color_attachment.format == Format::Undefined {
+                return Err(PipelineError::BlendWithoutFormat { attachment });
+            }
+            if let Some(&blend_op) = [blend.color_blend_op, blend.alpha_blend_op]
+                .iter()
+                .find(|blend_op| blend_op.is_advanced())
+            {
+                return Err(PipelineError::AdvancedBlendOp { attachment, blend_op });
+            }
+        }
+
+        [
+            (&self.mesh_shader_info, ShaderStage::Mesh),
+            (&self.vertex_shader_info, ShaderStage::Vertex),
+            (&self.tesselation_control_shader_info, ShaderStage::TesselationControl),
+            (&self.tesselation_evaluation_shader_info, ShaderStage::TesselationEvaluation),
+            (&self.fragment_shader_info, ShaderStage::Fragment),
+            (&self.task_shader_info, ShaderStage::Task),
+        ]
+        .into_iter()
+        .filter_map(|(shader, stage)| Some((shader.as_ref()?, stage)))
+        .try_for_each(|(shader, stage)| shader.validate(stage, self.push_constant_size).map(|_| ()))
+    }
+}
+
+/// Converted to the C layout on creation, see [`crate::Device::create_compute_pipeline`].
+pub struct ComputePipelineInfo<'a> {
+    pub shader_info: ShaderInfo<'a>,
+    pub push_constant_size: u32,
+    pub name: types::SmallString,
+    /// The `local_size` the shader declares, kept by the pipeline for
+    /// [`crate::CommandRecorder::dispatch_threads`]. Reflected from the byte code when `None`.
+    pub workgroup_size: std::option::Option<[u32; 3]>,
+}
+
+impl<'a> ComputePipelineInfo<'a> {
+    pub fn new(shader_info: ShaderInfo<'a>) -> Self {
+        Self {
+            shader_info,
+            push_constant_size: 0,
+            name: Default::default(),
+            workgroup_size: None,
+        }
+    }
+
+    pub fn push_constant_size(mut self, push_constant_size: u32) -> Self {
+        self.push_constant_size = push_constant_size;
+        self
+    }
+
+    /// Names longer than [`types::SmallString::MAX_LEN`] bytes are truncated.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = types::SmallString::truncated(name);
+        self
+    }
+
+    pub fn workgroup_size(mut self, workgroup_size: [u32; 3]) -> Self {
+        self.workgroup_size = Some(workgroup_size);
+        self
+    }
+}
+
 #[repr(C)]
-pub struct ComputePipelineInfo {
-    shader_info: types::Option<ShaderInfo>,
-    push_constant_size: u32,
-    name: types::SmallString,
+pub(crate) struct CComputePipelineInfo<'a> {
+    pub(crate) shader_info: types::Option<ShaderInfo<'a>>,
+    pub(crate) push_constant_size: u32,
+    pub(crate) name: types::SmallString,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_raster_pipeline_infos() {
+        let vertex = SpirV::from_words(&reflection::tests::stage_shader(ShaderStage::Vertex)).unwrap();
+        let fragment = SpirV::from_words(&reflection::tests::stage_shader(ShaderStage::Fragment)).unwrap();
+        let info = RasterPipelineInfo::default()
+            .vertex_shader(vertex.shader_info("main"))
+            .fragment_shader(fragment.shader_info("main"))
+            .color_attachment(Format::B8g8r8a8Srgb, None)
+            .color_attachment(Format::R16g16b16a16Sfloat, Some(BlendInfo::default()))
+            .depth_test(DepthTestInfo {
+                enable_depth_write: true,
+                ..Default::default()
+            })
+            .push_constant_size(40)
+            .name("triangle");
+
+        assert!(info.vertex_shader_info.as_ref().is_some());
+        assert!(info.fragment_shader_info.as_ref().is_some());
+        assert!(info.mesh_shader_info.as_ref().is_none());
+        assert_eq!(info.color_attachments_count, 2);
+        assert_eq!(info.color_attachments[0].format, Format::B8g8r8a8Srgb);
+        assert!(info.color_attachments[0].blend.as_ref().is_none());
+        assert_eq!(info.color_attachments[1].format, Format::R16g16b16a16Sfloat);
+        assert!(info.color_attachments[1].blend.as_ref().is_some());
+        assert!(info.color_attachments[2..].iter().all(|attachment| attachment.format == Format::Undefined));
+        assert!(info.depth_test.as_ref().unwrap().enable_depth_write);
+        assert_eq!(info.push_constant_size, 40);
+        assert_eq!(info.name.as_str(), "triangle");
+        assert!(info.validate(DeviceFlags::empty(), NVIDIA_VENDOR_ID).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "at most 8 color attachments")]
+    fn panics_on_too_many_color_attachments() {
+        (0..=MAX_COLOR_ATTACHMENTS).fold(RasterPipelineInfo::default(), |info, _| {
+            info.color_attachment(Format::R8g8b8a8Unorm, None)
+        });
+    }
+
+    #[test]
+    fn rejects_unsupported_raster_state() {
+        let fill_rectangle = RasterPipelineInfo::default().raster(RasterizerInfo {
+            polygon_mode: PolygonMode::FillRectangleNV,
+            ..Default::default()
+        });
+        assert!(fill_rectangle.validate(DeviceFlags::empty(), NVIDIA_VENDOR_ID).is_ok());
+        assert!(matches!(
+            fill_rectangle.validate(DeviceFlags::empty(), 0x1002),
+            Err(PipelineError::FillRectangleUnsupported { vendor_id: 0x1002 })
+        ));
+
+        let conservative = RasterPipelineInfo::default().raster(RasterizerInfo {
+            conservative_raster_info: Some(ConsevativeRasterInfo::default()).into(),
+            ..Default::default()
+        });
+        assert!(matches!(
+            conservative.validate(DeviceFlags::empty(), NVIDIA_VENDOR_ID),
+            Err(PipelineError::ConservativeRasterizationDisabled)
+        ));
+        assert!(conservative.validate(DeviceFlags::CONSERVATIVE_RASTERIZATION, NVIDIA_VENDOR_ID).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_shader_combinations() {
+        let [mesh, vertex, control, evaluation] = [
+            ShaderStage::Mesh,
+            ShaderStage::Vertex,
+            ShaderStage::TesselationControl,
+            ShaderStage::TesselationEvaluation,
+        ]
+        .map(|stage| SpirV::from_words(&reflection::tests::stage_shader(stage)).unwrap());
+        let mesh_and_vertex = RasterPipelineInfo::default()
+            .mesh_shader(mesh.shader_info("main"))
+            .vertex_shader(vertex.shader_info("main"));
+        assert!(matches!(
+            mesh_and_vertex.validate(DeviceFlags::empty(), NVIDIA_VENDOR_ID),
+            Err(PipelineError::MeshAndVertexShader)
+        ));
+
+        let control_only = RasterPipelineInfo::default().tesselation_control_shader(control.shader_info("main"));
+        assert!(matches!(
+            control_only.validate(DeviceFlags::empty(), NVIDIA_VENDOR_ID),
+            Err(PipelineError::IncompleteTesselation)
+        ));
+        let evaluation_only =
+            RasterPipelineInfo::default().tesselation_evaluation_shader(evaluation.shader_info("main"));
+        assert!(matches!(
+            evaluation_only.validate(DeviceFlags::empty(), NVIDIA_VENDOR_ID),
+            Err(PipelineError::IncompleteTesselation)
+        ));
+
+        let triangles = RasterPipelineInfo::default()
+            .tesselation_control_shader(control.shader_info("main"))
+            .tesselation_evaluation_shader(evaluation.shader_info("main"));
+        assert!(matches!(
+            triangles.validate(DeviceFlags::empty(), NVIDIA_VENDOR_ID),
+            Err(PipelineError::TesselationWithoutPatchList(PrimitiveTopology::TriangleList))
+        ));
+    }
+
+    #[test]
+    fn checks_entry_point_and_push_constant_size() {
+        let spirv = SpirV::from_words(&reflection::tests::compute_shader()).unwrap();
+        let entry_point = spirv.shader_info("main").validate(ShaderStage::Compute, 40).unwrap();
+        assert_eq!(entry_point.workgroup_size, Some([8, 4, 1]));
+        assert!(matches!(
+            spirv.shader_info("main").validate(ShaderStage::Compute, 32),
+            Err(PipelineError::PushConstantSizeTooSmall { push_constant_size: 32, declared: 40 })
+        ));
+        assert!(matches!(
+            spirv.shader_info("other").validate(ShaderStage::Compute, 40),
+            Err(PipelineError::MissingEntryPoint(name)) if name == "other"
+        ));
+    }
+
+    #[test]
+    fn rejects_shaders_of_the_wrong_stage() {
+        let vertex = SpirV::from_words(&reflection::tests::stage_shader(ShaderStage::Vertex)).unwrap();
+        let info = RasterPipelineInfo::default()
+            .vertex_shader(vertex.shader_info("main"))
+            .fragment_shader(vertex.shader_info("main"));
+        assert!(matches!(
+            info.validate(DeviceFlags::empty(), NVIDIA_VENDOR_ID),
+            Err(PipelineError::WrongStage {
+                expected: ShaderStage::Fragment,
+                found: Some(ShaderStage::Vertex),
+                ..
+            })
+        ));
+        assert!(matches!(
+            vertex.shader_info("main").validate(ShaderStage::Compute, 0),
+            Err(PipelineError::WrongStage { entry_point, expected: ShaderStage::Compute, .. }) if entry_point == "main"
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_blending() {
+        let blend = BlendInfo {
+            alpha_blend_op: BlendOp::MultiplyExt,
+            ..Default::default()
+        };
+        let info = RasterPipelineInfo::default()
+            .color_attachment(Format::R8g8b8a8Unorm, None)
+            .color_attachment(Format::R8g8b8a8Unorm, Some(blend));
+        assert!(matches!(
+            info.validate(DeviceFlags::empty(), NVIDIA_VENDOR_ID),
+            Err(PipelineError::AdvancedBlendOp { attachment: 1, blend_op: BlendOp::MultiplyExt })
+        ));
+
+        let info = RasterPipelineInfo::default().color_attachment(Format::Undefined, Some(BlendInfo::default()));
+        assert!(matches!(
+            info.validate(DeviceFlags::empty(), NVIDIA_VENDOR_ID),
+            Err(PipelineError::BlendWithoutFormat { attachment: 0 })
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserializes_partial_descriptions() {
+        let raster: RasterizerInfo = serde_json::from_str(
+            r#"{ "polygon_mode": "Line", "face_culling": ["BACK_BIT"], "conservative_raster_info": { "mode": "OverestimateExt", "size": 0.5 } }"#,
+        )
+        .unwrap();
+        assert_eq!(raster.polygon_mode, PolygonMode::Line);
+        assert_eq!(raster.face_culling, CullModeFlags::BACK_BIT);
+        assert_eq!(raster.primitive_topology, PrimitiveTopology::TriangleList);
+        assert_eq!(raster.line_width, 1.0);
+        let conservative = raster.conservative_raster_info.as_ref().unwrap();
+        assert_eq!((conservative.mode, conservative.size), (ConsevativeRasterizationModeEXT::OverestimateExt, 0.5));
+
+        let json = serde_json::to_string(&raster).unwrap();
+        let raster: RasterizerInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(raster.polygon_mode, PolygonMode::Line);
+
+        let mask = ColorComponentFlags::R | ColorComponentFlags::A;
+        assert_eq!(serde_json::to_string(&mask).unwrap(), r#"["R","A"]"#);
+        assert_eq!(serde_json::from_str::<ColorComponentFlags>(r#"["A","R"]"#).unwrap(), mask);
+        assert_eq!(serde_json::to_string(&CullModeFlags::FRONT_AND_BACK).unwrap(), r#"["FRONT_BIT","BACK_BIT"]"#);
+        assert!(serde_json::from_str::<CullModeFlags>(r#"["LEFT_BIT"]"#).is_err());
+        assert!(serde_json::from_str::<CullModeFlags>(r#"{ "bits": 64 }"#).is_err());
+    }
+
+    #[test]
+    fn rejects_names_that_do_not_fit() {
+        assert!(check_name(&"a".repeat(types::SmallString::MAX_LEN)).is_ok());
+        assert!(matches!(
+            check_name(&"a".repeat(types::SmallString::MAX_LEN + 1)),
+            Err(PipelineError::NameTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn truncates_long_builder_names() {
+        let name = "a".repeat(types::SmallString::MAX_LEN + 1);
+        let spirv = SpirV::from_words(&reflection::tests::compute_shader()).unwrap();
+        let info = ComputePipelineInfo::new(ShaderInfo::new(&spirv, &name)).name(&name);
+        assert_eq!(info.shader_info.entry_point.as_str(), &name[..types::SmallString::MAX_LEN]);
+        assert_eq!(info.name.as_str(), &name[..types::SmallString::MAX_LEN]);
+        let info = RasterPipelineInfo::default().name(&name);
+        assert_eq!(info.name.as_str(), &name[..types::SmallString::MAX_LEN]);
+    }
 }
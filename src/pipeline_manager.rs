@@ -0,0 +1,501 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use crate::include::{normalize, ShaderFileSystem};
+use crate::pipeline::*;
+use crate::shader::*;
+use crate::shader_compiler::*;
+use crate::types::{ComputePipeline, Format, RasterPipeline};
+use crate::Device;
+
+/// A pipeline owned by a [`PipelineManager`]. The manager swaps in a new pipeline when its shaders
+/// are rebuilt, [`PipelineHandle::get`] always returns the latest one.
+pub struct PipelineHandle<P>(Arc<RwLock<Arc<P>>>);
+
+impl<P> Clone for PipelineHandle<P> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<P> PipelineHandle<P> {
+    fn new(pipeline: P) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(pipeline))))
+    }
+
+    /// The returned pipeline stays valid after a reload, commands recorded with it keep using it.
+    pub fn get(&self) -> Arc<P> {
+        self.0.read().unwrap().clone()
+    }
+
+    fn set(&self, pipeline: P) {
+        *self.0.write().unwrap() = Arc::new(pipeline);
+    }
+}
+
+/// A shader stage compiled from a file. `.wgsl` files are compiled as WGSL, everything else as GLSL.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShaderSource {
+    pub path: PathBuf,
+    #[cfg_attr(feature = "serde", serde(default = "main_entry_point"))]
+    pub entry_point: String,
+}
+
+fn main_entry_point() -> String {
+    "main".to_owned()
+}
+
+impl ShaderSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            entry_point: main_entry_point(),
+        }
+    }
+
+    pub fn entry_point(mut self, entry_point: &str) -> Self {
+        self.entry_point = entry_point.to_owned();
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComputePipelineSource {
+    pub shader: ShaderSource,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub push_constant_size: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub name: String,
+}
+
+/// The owned counterpart of [`RasterPipelineInfo`], with shader sources in place of byte code.
+///
+/// With the `serde` feature, descriptions can be loaded from data files, every field but the
+/// shaders has a default:
+///
+/// ```ignore
+/// let source: RasterPipelineSource = serde_json::from_str(r#"{
+///     "vertex_shader": { "path": "shaders/mesh.vert" },
+///     "fragment_shader": { "path": "shaders/mesh.frag" },
+///     "color_attachments": [["B8g8r8a8Srgb", null]],
+///     "depth_test": { "depth_attachment_format": "D32Sfloat", "enable_depth_write": true },
+///     "raster": { "face_culling": ["BACK_BIT"] },
+///     "name": "mesh"
+/// }"#)?;
+/// let pipeline = manager.add_raster_pipeline(source)?;
+/// ```
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RasterPipelineSource {
+    pub mesh_shader: std::option::Option<ShaderSource>,
+    pub vertex_shader: std::option::Option<ShaderSource>,
+    pub tesselation_control_shader: std::option::Option<ShaderSource>,
+    pub tesselation_evaluation_shader: std::option::Option<ShaderSource>,
+    pub fragment_shader: std::option::Option<ShaderSource>,
+    pub task_shader: std::option::Option<ShaderSource>,
+    pub color_attachments: Vec<(Format, std::option::Option<BlendInfo>)>,
+    pub depth_test: std::option::Option<DepthTestInfo>,
+    pub tesselation: std::option::Option<TesselationInfo>,
+    pub raster: RasterizerInfo,
+    pub push_constant_size: u32,
+    pub name: String,
+}
+
+#[derive(Clone, Default)]
+pub struct PipelineManagerInfo {
+    /// Preprocessor definitions passed to every GLSL shader.
+    pub defines: Vec<(String, String)>,
+    /// Shader files and their includes are read through it.
+    pub file_system: ShaderFileSystem,
+}
+
+/// The files a pipeline was built from, with their modification times at the time of the build.
+struct Dependencies(Vec<(PathBuf, std::option::Option<SystemTime>)>);
+
+impl Dependencies {
+    fn new(mut paths: Vec<PathBuf>) -> Self {
+        paths.sort();
+        paths.dedup();
+        Self(
+            paths
+                .into_iter()
+                .map(|path| {
+                    let modified = modified(&path);
+                    (path, modified)
+                })
+                .collect(),
+        )
+    }
+
+    /// Dependencies are normalized by the preprocessor, `path` does not have to be.
+    fn contains(&self, path: &Path) -> bool {
+        let path = normalize(path);
+        self.0.iter().any(|(dependency, _)| *dependency == path)
+    }
+
+    /// Whether a file was modified, created or deleted since the last call.
+    fn changed(&mut self) -> bool {
+        let mut changed = false;
+        for (path, last_modified) in &mut self.0 {
+            let modified = modified(path);
+            changed |= modified != *last_modified;
+            *last_modified = modified;
+        }
+        changed
+    }
+}
+
+fn modified(path: &Path) -> std::option::Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+enum Pipeline {
+    Compute(ComputePipelineSource, PipelineHandle<ComputePipeline>),
+    Raster(RasterPipelineSource, PipelineHandle<RasterPipeline>),
+}
+
+struct ManagedPipeline {
+    pipeline: Pipeline,
+    dependencies: Dependencies,
+    /// A virtual file it depends on was changed.
+    dirty: bool,
+}
+
+/// Builds pipelines from shader files and rebuilds them when the files change.
+///
+/// ```ignore
+/// let mut manager = PipelineManager::new(&device, Default::default(), |name, error| eprintln!("{}: {}", name, error));
+/// let pipeline = manager.add_compute_pipeline(ComputePipelineSource {
+///     shader: ShaderSource::new("shaders/blur.comp"),
+///     push_constant_size: 16,
+///     name: "blur".to_owned(),
+/// })?;
+/// loop {
+///     manager.reload();
+///     recorder.set_compute_pipeline(&pipeline.get());
+///     // ...
+/// }
+/// ```
+pub struct PipelineManager<'a> {
+    device: &'a Device,
+    info: PipelineManagerInfo,
+    on_error: Box<dyn FnMut(&str, &PipelineError) + 'a>,
+    pipelines: Vec<ManagedPipeline>,
+}
+
+impl<'a> PipelineManager<'a> {
+    /// `on_error` is called with the pipeline name when a rebuild fails, the handle then keeps
+    /// the previous pipeline.
+    pub fn new(
+        device: &'a Device,
+        info: PipelineManagerInfo,
+        on_error: impl FnMut(&str, &PipelineError) + 'a,
+    ) -> Self {
+        Self {
+            device,
+            info,
+            on_error: Box::new(on_error),
+            pipelines: Vec::new(),
+        }
+    }
+
+    /// Errors are returned directly here, there is no previous pipeline to fall back to.
+    pub fn add_compute_pipeline(
+        &mut self,
+        source: ComputePipelineSource,
+    ) -> std::result::Result<PipelineHandle<ComputePipeline>, PipelineError> {
+        let (pipeline, dependencies) = self.build_compute_pipeline(&source)?;
+        let handle = PipelineHandle::new(pipeline);
+        self.pipelines.push(ManagedPipeline {
+            pipeline: Pipeline::Compute(source, handle.clone()),
+            dependencies: Dependencies::new(dependencies),
+            dirty: false,
+        });
+        Ok(handle)
+    }
+
+    /// See [`PipelineManager::add_compute_pipeline`].
+    pub fn add_raster_pipeline(
+        &mut self,
+        source: RasterPipelineSource,
+    ) -> std::result::Result<PipelineHandle<RasterPipeline>, PipelineError> {
+        let (pipeline, dependencies) = self.build_raster_pipeline(&source)?;
+        let handle = PipelineHandle::new(pipeline);
+        self.pipelines.push(ManagedPipeline {
+            pipeline: Pipeline::Raster(source, handle.clone()),
+            dependencies: Dependencies::new(dependencies),
+            dirty: false,
+        });
+        Ok(handle)
+    }
+
+    /// Replaces a virtual file, the pipelines that include it are rebuilt by the next
+    /// [`PipelineManager::reload`].
+    pub fn set_virtual_file(&mut self, path: impl Into<PathBuf>, source: impl Into<String>) {
+        let path = path.into();
+        for pipeline in &mut self.pipelines {
+            pipeline.dirty |= pipeline.dependencies.contains(&path);
+        }
+        self.info.file_system.set_virtual_file(path, source);
+    }
+
+    /// The names of the pipelines built from `path`, directly or through includes, as of their
+    /// last successful build.
+    pub fn pipelines_depending_on(&self, path: &Path) -> Vec<&str> {
+        self.pipelines
+            .iter()
+            .filter(|pipeline| pipeline.dependencies.contains(path))
+            .map(|pipeline| match &pipeline.pipeline {
+                Pipeline::Compute(source, _) => source.name.as_str(),
+                Pipeline::Raster(source, _) => source.name.as_str(),
+            })
+            .collect()
+    }
+
+    /// Rebuilds the pipelines whose files or includes changed since the last call and returns how
+    /// many were swapped. Meant to be called once per frame.
+    pub fn reload(&mut self) -> usize {
+        let mut reloaded = 0;
+        for index in 0..self.pipelines.len() {
+            let changed = self.pipelines[index].dependencies.changed();
+            let dirty = std::mem::take(&mut self.pipelines[index].dirty);
+            if !changed && !dirty {
+                continue;
+            }
+            let (name, result) = match &self.pipelines[index].pipeline {
+                Pipeline::Compute(source, handle) => (
+                    &source.name,
+                    self.build_compute_pipeline(source).map(|(pipeline, dependencies)| {
+                        handle.set(pipeline);
+                        dependencies
+                    }),
+                ),
+                Pipeline::Raster(source, handle) => (
+                    &source.name,
+                    self.build_raster_pipeline(source).map(|(pipeline, dependencies)| {
+                        handle.set(pipeline);
+                        dependencies
+                    }),
+                ),
+            };
+            match result {
+                Ok(dependencies) => {
+                    self.pipelines[index].dependencies = Dependencies::new(dependencies);
+                    reloaded += 1;
+                }
+                Err(error) => (self.on_error)(name, &error),
+            }
+        }
+        reloaded
+    }
+
+    fn build_compute_pipeline(
+        &self,
+        source: &ComputePipelineSource,
+    ) -> std::result::Result<(ComputePipeline, Vec<PathBuf>), PipelineError> {
+        check_name(&source.name)?;
+        check_name(&source.shader.entry_point)?;
+        let (spirv, dependencies) = self.compile(&source.shader, ShaderStage::Compute)?;
+        let info = ComputePipelineInfo::new(spirv.shader_info(&source.shader.entry_point))
+            .push_constant_size(source.push_constant_size)
+            .name(&source.name);
+        let pipeline = self.device.create_compute_pipeline(&info)?;
+        Ok((pipeline, dependencies))
+    }
+
+    fn build_raster_pipeline(
+        &self,
+        source: &RasterPipelineSource,
+    ) -> std::result::Result<(RasterPipeline, Vec<PathBuf>), PipelineError> {
+        if source.color_attachments.len() > MAX_COLOR_ATTACHMENTS {
+            return Err(PipelineError::TooManyColorAttachments(source.color_attachments.len()));
+        }
+        check_name(&source.name)?;
+        let stages = [
+            (&source.mesh_shader, ShaderStage::Mesh),
+            (&source.vertex_shader, ShaderStage::Vertex),
+            (&source.tesselation_control_shader, ShaderStage::TesselationControl),
+            (&source.tesselation_evaluation_shader, ShaderStage::TesselationEvaluation),
+            (&source.fragment_shader, ShaderStage::Fragment),
+            (&source.task_shader, ShaderStage::Task),
+        ];
+        let mut compiled = Vec::new();
+        let mut dependencies = Vec::new();
+        for (shader, stage) in stages {
+            if let Some(shader) = shader {
+                check_name(&shader.entry_point)?;
+                let (spirv, files) = self.compile(shader, stage)?;
+                compiled.push((stage, shader, spirv));
+                dependencies.extend(files);
+            }
+        }
+
+        let mut info = RasterPipelineInfo::default();
+        for (stage, shader, spirv) in &compiled {
+            let shader_info = spirv.shader_info(&shader.entry_point);
+            info = match stage {
+                ShaderStage::Mesh => info.mesh_shader(shader_info),
+                ShaderStage::Vertex => info.vertex_shader(shader_info),
+                ShaderStage::TesselationControl => info.tesselation_control_shader(shader_info),
+                ShaderStage::TesselationEvaluation => info.tesselation_evaluation_shader(shader_info),
+                ShaderStage::Fragment => info.fragment_shader(shader_info),
+                ShaderStage::Task => info.task_shader(shader_info),
+                ShaderStage::Compute => unreachable!(),
+            };
+        }
+        for &(format, blend) in &source.color_attachments {
+            info = info.color_attachment(format, blend);
+        }
+        if let Some(depth_test) = source.depth_test {
+            info = info.depth_test(depth_test);
+        }
+        if let Some(tesselation) = source.tesselation {
+            info = info.tesselation(tesselation);
+        }
+        info = info
+            .raster(source.raster)
+            .push_constant_size(source.push_constant_size)
+            .name(&source.name);
+
+        let pipeline = self.device.create_raster_pipeline(&info)?;
+        Ok((pipeline, dependencies))
+    }
+
+    // Also returns every file the shader was built from.
+    fn compile(
+        &self,
+        shader: &ShaderSource,
+        stage: ShaderStage,
+    ) -> std::result::Result<(SpirV, Vec<PathBuf>), ShaderError> {
+        let preprocessed = self.info.file_system.preprocess(&shader.path)?;
+        let language = match shader.path.extension().and_then(|extension| extension.to_str()) {
+            Some("wgsl") => ShaderLanguage::Wgsl,
+            _ => ShaderLanguage::Glsl,
+        };
+        let defines: Vec<(&str, &str)> = match language {
+            ShaderLanguage::Glsl => self
+                .info
+                .defines
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect(),
+            ShaderLanguage::Wgsl => Vec::new(),
+        };
+        let spirv = compile_shader(&ShaderCompileInfo {
+            source: &preprocessed.source,
+            path: &shader.path,
+            language,
+            stage,
+            entry_point: &shader.entry_point,
+            defines: &defines,
+        })
+        .map_err(|error| match error {
+            ShaderError::Compile(messages) => ShaderError::Compile(
+                messages.into_iter().map(|message| preprocessed.map_message(message)).collect(),
+            ),
+            error => error,
+        })?;
+        Ok((spirv, preprocessed.files))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::null_backend::tests::device;
+
+    #[test]
+    fn dependencies_notice_modification_and_deletion() {
+        let path = std::env::temp_dir().join(format!("daxa-rs-dependencies-{}.glsl", std::process::id()));
+        std::fs::write(&path, "void main() {}").unwrap();
+        let mut dependencies = Dependencies::new(vec![path.clone()]);
+        assert!(!dependencies.changed());
+
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(10)).unwrap();
+        assert!(dependencies.changed());
+        assert!(!dependencies.changed());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(dependencies.changed());
+    }
+
+    const SHADER: &str = "#version 450\n#include \"common.glsl\"\nlayout(local_size_x = SIZE) in;\nvoid main() {}";
+
+    fn manager<'a>(device: &'a Device, errors: &'a RefCell<Vec<String>>) -> PipelineManager<'a> {
+        let info = PipelineManagerInfo {
+            defines: Vec::new(),
+            file_system: ShaderFileSystem::default()
+                .virtual_file("shaders/blur.comp", SHADER)
+                .virtual_file("shaders/common.glsl", "#define SIZE 8"),
+        };
+        PipelineManager::new(device, info, move |name, error| {
+            errors.borrow_mut().push(format!("{}: {}", name, error))
+        })
+    }
+
+    fn blur() -> ComputePipelineSource {
+        ComputePipelineSource {
+            shader: ShaderSource::new("shaders/blur.comp"),
+            push_constant_size: 0,
+            name: "blur".to_owned(),
+        }
+    }
+
+    #[test]
+    fn rebuilds_pipelines_after_virtual_file_changes() {
+        let device = device();
+        let errors = RefCell::new(Vec::new());
+        let mut manager = manager(&device, &errors);
+        let pipeline = manager.add_compute_pipeline(blur()).unwrap();
+        let first = pipeline.get();
+        assert_eq!(first.workgroup_size(), Some([8, 1, 1]));
+        assert_eq!(manager.reload(), 0);
+
+        manager.set_virtual_file("shaders/unrelated.glsl", "");
+        assert_eq!(manager.reload(), 0);
+        assert!(Arc::ptr_eq(&first, &pipeline.get()));
+
+        manager.set_virtual_file("./shaders/common.glsl", "#define SIZE 16");
+        assert_eq!(manager.reload(), 1);
+        assert_eq!(pipeline.get().workgroup_size(), Some([16, 1, 1]));
+        assert_eq!(manager.reload(), 0);
+        assert!(errors.borrow().is_empty());
+    }
+
+    #[test]
+    fn keeps_the_previous_pipeline_when_a_rebuild_fails() {
+        let device = device();
+        let errors = RefCell::new(Vec::new());
+        let mut manager = manager(&device, &errors);
+        let pipeline = manager.add_compute_pipeline(blur()).unwrap();
+        let first = pipeline.get();
+
+        manager.set_virtual_file("shaders/common.glsl", "#define SIZE 8\nfloat broken = ;");
+        assert_eq!(manager.reload(), 0);
+        assert_eq!(errors.borrow().len(), 1);
+        assert!(errors.borrow()[0].starts_with("blur: "));
+        assert!(Arc::ptr_eq(&first, &pipeline.get()));
+
+        // The failed build is not retried until a file changes again.
+        assert_eq!(manager.reload(), 0);
+        assert_eq!(errors.borrow().len(), 1);
+        manager.set_virtual_file("shaders/common.glsl", "#define SIZE 4");
+        assert_eq!(manager.reload(), 1);
+        assert_eq!(pipeline.get().workgroup_size(), Some([4, 1, 1]));
+    }
+
+    #[test]
+    fn dependencies_match_unnormalized_paths() {
+        let dependencies = Dependencies::new(vec![PathBuf::from("shaders/common.glsl")]);
+        assert!(dependencies.contains(Path::new("./shaders/common.glsl")));
+        assert!(dependencies.contains(Path::new("shaders/../shaders/common.glsl")));
+        assert!(!dependencies.contains(Path::new("common.glsl")));
+    }
+}
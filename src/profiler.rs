@@ -0,0 +1,206 @@
+use std::cell::RefCell;
+use std::time::Duration;
+
+use crate::command_recorder::CommandRecorder;
+use crate::device::Device;
+use crate::types::*;
+
+#[derive(Debug)]
+pub enum ProfilerError {
+    Daxa(Result),
+    /// `frames_in_flight` is 0, there would be no frame to record into.
+    NoFramesInFlight,
+    /// `frames_in_flight * max_scopes_per_frame * 2` queries do not fit into a `u32`.
+    TooManyQueries { frames_in_flight: u32, max_scopes_per_frame: u32 },
+}
+
+impl std::fmt::Display for ProfilerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProfilerError::Daxa(result) => write!(f, "daxa returned {:?}", result),
+            ProfilerError::NoFramesInFlight => write!(f, "a gpu profiler needs at least one frame in flight"),
+            ProfilerError::TooManyQueries {
+                frames_in_flight,
+                max_scopes_per_frame,
+            } => write!(
+                f,
+                "{} frames in flight with {} scopes each need more queries than fit into a u32",
+                frames_in_flight, max_scopes_per_frame
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProfilerError {}
+
+impl From<Result> for ProfilerError {
+    fn from(result: Result) -> Self {
+        ProfilerError::Daxa(result)
+    }
+}
+
+/// Collects named gpu timings per frame.
+///
+/// Every frame in flight owns its own range of queries. Results of a frame are only read back
+/// once the profiler cycles around to its queries again, `frames_in_flight` frames later, so
+/// reading them never stalls on the gpu.
+pub struct GpuProfiler {
+    query_pool: TimelineQueryPool,
+    timestamp_period: f32,
+    frames_in_flight: u32,
+    max_scopes: u32,
+    current_frame: usize,
+    // Names of the scopes written into each frame's query range, in query order.
+    frame_scopes: RefCell<Vec<Vec<String>>>,
+    results: Vec<(String, Duration)>,
+}
+
+impl GpuProfiler {
+    pub fn new(
+        device: &Device,
+        frames_in_flight: u32,
+        max_scopes_per_frame: u32,
+    ) -> std::result::Result<Self, ProfilerError> {
+        if frames_in_flight == 0 {
+            return Err(ProfilerError::NoFramesInFlight);
+        }
+        let query_count = frames_in_flight
+            .checked_mul(max_scopes_per_frame)
+            .and_then(|count| count.checked_mul(2))
+            .ok_or(ProfilerError::TooManyQueries {
+                frames_in_flight,
+                max_scopes_per_frame,
+            })?;
+        let query_pool = device.create_timeline_query_pool(&TimelineQueryPoolInfo {
+            query_count,
+            name: "gpu profiler".into(),
+        })?;
+        Ok(Self {
+            query_pool,
+            timestamp_period: device.properties().limits.timestampPeriod,
+            frames_in_flight,
+            max_scopes: max_scopes_per_frame,
+            current_frame: frames_in_flight as usize - 1,
+            frame_scopes: RefCell::new(vec![Vec::new(); frames_in_flight as usize]),
+            results: Vec::new(),
+        })
+    }
+
+    /// Moves on to the next frame's queries. Reads back the timings that were recorded into them
+    /// `frames_in_flight` frames ago and records the reset of the queries into `recorder`.
+    ///
+    /// If any of those queries is not available yet, the timings of that frame are dropped and
+    /// [`Self::results`] keeps the previous frame's.
+    pub fn begin_frame(&mut self, recorder: &CommandRecorder) -> std::result::Result<(), Result> {
+        self.current_frame = (self.current_frame + 1) % self.frames_in_flight as usize;
+        let first_query = self.first_query(self.current_frame);
+
+        let scopes = std::mem::take(&mut self.frame_scopes.get_mut()[self.current_frame]);
+        if !scopes.is_empty() {
+            let ticks = self.query_pool.query_ticks(first_query, scopes.len() as u32 * 2)?;
+            // Keep showing the last complete frame rather than waiting for this one.
+            if ticks.iter().all(|ticks| ticks.is_some()) {
+                self.results = scopes
+                    .into_iter()
+                    .zip(ticks.chunks_exact(2))
+                    .map(|(name, pair)| {
+                        let elapsed = pair[1].unwrap().saturating_sub(pair[0].unwrap());
+                        (name, ticks_to_duration(elapsed, self.timestamp_period))
+                    })
+                    .collect();
+            }
+        }
+
+        recorder.reset_timestamps(&self.query_pool, first_query, self.max_scopes * 2);
+        Ok(())
+    }
+
+    /// Times all commands recorded into `recorder` until the returned guard is dropped.
+    /// Scopes past `max_scopes_per_frame` in a frame are silently not measured.
+    pub fn scope<'a>(&'a self, recorder: &'a CommandRecorder, name: &str) -> GpuProfilerScope<'a> {
+        let mut frame_scopes = self.frame_scopes.borrow_mut();
+        let scopes = &mut frame_scopes[self.current_frame];
+        let query_index = if (scopes.len() as u32) < self.max_scopes {
+            let query_index = self.first_query(self.current_frame) + scopes.len() as u32 * 2;
+            scopes.push(name.to_owned());
+            recorder.write_timestamp(&self.query_pool, PipelineStageFlags::ALL_COMMANDS_BIT, query_index);
+            Some(query_index)
+        } else {
+            None
+        };
+        GpuProfilerScope {
+            profiler: self,
+            recorder,
+            query_index,
+        }
+    }
+
+    /// The timings of the most recent frame whose queries were all available.
+    pub fn results(&self) -> &[(String, Duration)] {
+        &self.results
+    }
+
+    fn first_query(&self, frame: usize) -> u32 {
+        frame as u32 * self.max_scopes * 2
+    }
+}
+
+pub struct GpuProfilerScope<'a> {
+    profiler: &'a GpuProfiler,
+    recorder: &'a CommandRecorder,
+    query_index: std::option::Option<u32>,
+}
+
+impl<'a> Drop for GpuProfilerScope<'a> {
+    fn drop(&mut self) {
+        if let Some(query_index) = self.query_index {
+            self.recorder.write_timestamp(
+                &self.profiler.query_pool,
+                PipelineStageFlags::ALL_COMMANDS_BIT,
+                query_index + 1,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::null_backend::tests::device;
+
+    #[test]
+    fn rejects_invalid_query_counts() {
+        let device = device();
+        assert!(matches!(GpuProfiler::new(&device, 0, 4), Err(ProfilerError::NoFramesInFlight)));
+        assert!(matches!(
+            GpuProfiler::new(&device, 2, u32::MAX / 2),
+            Err(ProfilerError::TooManyQueries {
+                frames_in_flight: 2,
+                max_scopes_per_frame: _,
+            })
+        ));
+    }
+
+    #[test]
+    fn cycles_through_frame_query_ranges() {
+        let device = device();
+        let recorder = device.create_command_recorder(&Default::default()).unwrap();
+        let mut profiler = GpuProfiler::new(&device, 2, 3).unwrap();
+
+        profiler.begin_frame(&recorder).unwrap();
+        assert_eq!(profiler.scope(&recorder, "a").query_index, Some(0));
+        assert_eq!(profiler.scope(&recorder, "b").query_index, Some(2));
+        assert_eq!(profiler.scope(&recorder, "c").query_index, Some(4));
+        assert_eq!(profiler.scope(&recorder, "dropped").query_index, None);
+
+        profiler.begin_frame(&recorder).unwrap();
+        assert!(profiler.results().is_empty());
+        assert_eq!(profiler.scope(&recorder, "d").query_index, Some(6));
+
+        // Back on the first frame's queries, which are read back before being reused.
+        profiler.begin_frame(&recorder).unwrap();
+        let names: Vec<_> = profiler.results().iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(profiler.scope(&recorder, "e").query_index, Some(0));
+    }
+}
@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+
+use crate::shader::*;
+
+/// Storage classes of the variables and pointers a shader declares.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum StorageClass {
+    UniformConstant,
+    Input,
+    Uniform,
+    Output,
+    Workgroup,
+    CrossWorkgroup,
+    Private,
+    Function,
+    Generic,
+    PushConstant,
+    AtomicCounter,
+    Image,
+    StorageBuffer,
+    PhysicalStorageBuffer,
+    TaskPayloadWorkgroup,
+    Other(u32),
+}
+
+impl From<u32> for StorageClass {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => StorageClass::UniformConstant,
+            1 => StorageClass::Input,
+            2 => StorageClass::Uniform,
+            3 => StorageClass::Output,
+            4 => StorageClass::Workgroup,
+            5 => StorageClass::CrossWorkgroup,
+            6 => StorageClass::Private,
+            7 => StorageClass::Function,
+            8 => StorageClass::Generic,
+            9 => StorageClass::PushConstant,
+            10 => StorageClass::AtomicCounter,
+            11 => StorageClass::Image,
+            12 => StorageClass::StorageBuffer,
+            5349 => StorageClass::PhysicalStorageBuffer,
+            5402 => StorageClass::TaskPayloadWorkgroup,
+            other => StorageClass::Other(other),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EntryPoint {
+    pub name: String,
+    /// `None` for execution models daxa has no pipeline stage for, like geometry shaders.
+    pub stage: std::option::Option<ShaderStage>,
+    /// Only known for compute, task and mesh shaders.
+    pub workgroup_size: std::option::Option<[u32; 3]>,
+}
+
+/// What pipeline creation needs to know about a shader, read from its SPIR-V.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ShaderReflection {
+    pub entry_points: Vec<EntryPoint>,
+    /// The size of the push constant block in bytes, 0 if there is none.
+    pub push_constant_size: u32,
+    /// Sorted, without duplicates.
+    pub storage_classes: Vec<StorageClass>,
+}
+
+impl ShaderReflection {
+    pub fn entry_point(&self, name: &str) -> std::option::Option<&EntryPoint> {
+        self.entry_points.iter().find(|entry_point| entry_point.name == name)
+    }
+}
+
+impl SpirV {
+    pub fn reflect(&self) -> std::result::Result<ShaderReflection, ShaderError> {
+        reflect(self.words())
+    }
+}
+
+mod op {
+    pub const ENTRY_POINT: u16 = 15;
+    pub const EXECUTION_MODE: u16 = 16;
+    pub const TYPE_INT: u16 = 21;
+    pub const TYPE_FLOAT: u16 = 22;
+    pub const TYPE_VECTOR: u16 = 23;
+    pub const TYPE_MATRIX: u16 = 24;
+    pub const TYPE_ARRAY: u16 = 28;
+    pub const TYPE_STRUCT: u16 = 30;
+    pub const TYPE_POINTER: u16 = 32;
+    pub const CONSTANT: u16 = 43;
+    pub const CONSTANT_COMPOSITE: u16 = 44;
+    pub const SPEC_CONSTANT: u16 = 50;
+    pub const SPEC_CONSTANT_COMPOSITE: u16 = 51;
+    pub const VARIABLE: u16 = 59;
+    pub const DECORATE: u16 = 71;
+    pub const MEMBER_DECORATE: u16 = 72;
+    pub const EXECUTION_MODE_ID: u16 = 331;
+}
+
+const EXECUTION_MODE_LOCAL_SIZE: u32 = 17;
+const EXECUTION_MODE_LOCAL_SIZE_ID: u32 = 38;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_OFFSET: u32 = 35;
+const BUILT_IN_WORKGROUP_SIZE: u32 = 25;
+
+enum Type {
+    Scalar { size: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Array { element: u32, length: u32 },
+    Struct { members: Vec<u32> },
+    // Only physical storage buffer pointers can be part of a block, as 64 bit addresses.
+    Pointer,
+}
+
+#[derive(Default)]
+struct Module {
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, Vec<u32>>,
+    array_strides: HashMap<u32, u32>,
+    member_offsets: HashMap<(u32, u32), u32>,
+    member_matrix_strides: HashMap<(u32, u32), u32>,
+}
+
+impl Module {
+    fn constant(&self, id: u32) -> std::option::Option<u32> {
+        self.constants.get(&id)?.first().copied()
+    }
+
+    /// Types can only contain types declared before them, which keeps [`Module::size_of`] from
+    /// recursing forever on a type that contains itself.
+    fn insert_type(&mut self, id: u32, ty: Type) -> std::result::Result<(), ShaderError> {
+        let contained = match &ty {
+            Type::Scalar { .. } | Type::Pointer => &[][..],
+            Type::Vector { component, .. } => std::slice::from_ref(component),
+            Type::Matrix { column, .. } => std::slice::from_ref(column),
+            Type::Array { element, .. } => std::slice::from_ref(element),
+            Type::Struct { members } => members.as_slice(),
+        };
+        if !contained.iter().all(|id| self.types.contains_key(id)) {
+            return Err(ShaderError::Malformed("type contains a type declared after it"));
+        }
+        if self.types.insert(id, ty).is_some() {
+            return Err(ShaderError::Malformed("type id declared twice"));
+        }
+        Ok(())
+    }
+
+    /// The size of a type inside of a block, following the explicit layout decorations.
+    fn size_of(&self, id: u32, matrix_stride: std::option::Option<u32>) -> std::result::Result<u32, ShaderError> {
+        let overflow = || ShaderError::Malformed("type size overflows");
+        match self.types.get(&id) {
+            Some(Type::Scalar { size }) => Ok(*size),
+            Some(Type::Vector { component, count }) => {
+                self.size_of(*component, None)?.checked_mul(*count).ok_or_else(overflow)
+            }
+            Some(Type::Matrix { column, count }) => {
+                let stride = match matrix_stride {
+                    Some(stride) => stride,
+                    None => self.size_of(*column, None)?,
+                };
+                stride.checked_mul(*count).ok_or_else(overflow)
+            }
+            Some(Type::Array { element, length }) => {
+                let stride = match self.array_strides.get(&id) {
+                    Some(&stride) => stride,
+                    None => self.size_of(*element, None)?,
+                };
+                stride.checked_mul(*length).ok_or_else(overflow)
+            }
+            Some(Type::Struct { members }) => {
+                let mut size = 0;
+                for (member, &member_type) in (0..).zip(members) {
+                    let offset = self.member_offsets.get(&(id, member)).copied().unwrap_or(0);
+                    let stride = self.member_matrix_strides.get(&(id, member)).copied();
+                    let end = offset.checked_add(self.size_of(member_type, stride)?).ok_or_else(overflow)?;
+                    size = size.max(end);
+                }
+                Ok(size)
+            }
+            Some(Type::Pointer) => Ok(8),
+            None => Ok(0),
+        }
+    }
+}
+
+fn stage(execution_model: u32) -> std::option::Option<ShaderStage> {
+    match execution_model {
+        0 => Some(ShaderStage::Vertex),
+        1 => Some(ShaderStage::TesselationControl),
+        2 => Some(ShaderStage::TesselationEvaluation),
+        4 => Some(ShaderStage::Fragment),
+        5 => Some(ShaderStage::Compute),
+        5267 | 5364 => Some(ShaderStage::Task),
+        5268 | 5365 => Some(ShaderStage::Mesh),
+        _ => None,
+    }
+}
+
+// Literal strings are nul terminated utf-8, packed little endian into words.
+fn literal_string(words: &[u32]) -> std::result::Result<(String, usize), ShaderError> {
+    let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+    let len = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or(ShaderError::Malformed("unterminated string"))?;
+    let string = String::from_utf8(bytes[..len].to_vec()).map_err(|_| ShaderError::Malformed("string is not utf-8"))?;
+    Ok((string, len / 4 + 1))
+}
+
+pub(crate) fn reflect(words: &[u32]) -> std::result::Result<ShaderReflection, ShaderError> {
+    // Skips the header, its magic number is checked when creating the `SpirV`.
+    let mut instructions = words.get(5..).unwrap_or_default();
+    let mut module = Module::default();
+    let mut entry_points = Vec::new();
+    let mut entry_point_ids = Vec::new();
+    let mut local_sizes = HashMap::new();
+    let mut local_size_ids = HashMap::new();
+    let mut workgroup_size_constant = None;
+    let mut push_constant_types = Vec::new();
+    let mut pointee_types = HashMap::new();
+    let mut storage_classes = Vec::new();
+
+    while let Some(&first) = instructions.first() {
+        let word_count = (first >> 16) as usize;
+        let opcode = (first & 0xffff) as u16;
+        if word_count == 0 || word_count > instructions.len() {
+            return Err(ShaderError::Malformed("instruction word count"));
+        }
+        let operands = &instructions[1..word_count];
+        instructions = &instructions[word_count..];
+        let operand = |index: usize| operands.get(index).copied().ok_or(ShaderError::Malformed("missing operand"));
+
+        match opcode {
+            op::ENTRY_POINT => {
+                let (name, _) = literal_string(operands.get(2..).unwrap_or_default())?;
+                entry_point_ids.push(operand(1)?);
+                entry_points.push(EntryPoint {
+                    name,
+                    stage: stage(operand(0)?),
+                    workgroup_size: None,
+                });
+            }
+            op::EXECUTION_MODE if operand(1)? == EXECUTION_MODE_LOCAL_SIZE => {
+                local_sizes.insert(operand(0)?, [operand(2)?, operand(3)?, operand(4)?]);
+            }
+            // The components are constants declared later, so they are looked up at the end.
+            op::EXECUTION_MODE_ID if operand(1)? == EXECUTION_MODE_LOCAL_SIZE_ID => {
+                local_size_ids.insert(operand(0)?, [operand(2)?, operand(3)?, operand(4)?]);
+            }
+            op::TYPE_INT | op::TYPE_FLOAT => {
+                module.insert_type(operand(0)?, Type::Scalar { size: operand(1)? / 8 })?;
+            }
+            op::TYPE_VECTOR => {
+                module.insert_type(operand(0)?, Type::Vector { component: operand(1)?, count: operand(2)? })?;
+            }
+            op::TYPE_MATRIX => {
+                module.insert_type(operand(0)?, Type::Matrix { column: operand(1)?, count: operand(2)? })?;
+            }
+            op::TYPE_ARRAY => {
+                let length = module.constant(operand(2)?).unwrap_or(0);
+                module.insert_type(operand(0)?, Type::Array { element: operand(1)?, length })?;
+            }
+            op::TYPE_STRUCT => {
+                module.insert_type(operand(0)?, Type::Struct { members: operands[1..].to_vec() })?;
+            }
+            op::TYPE_POINTER => {
+                storage_classes.push(StorageClass::from(operand(1)?));
+                pointee_types.insert(operand(0)?, operand(2)?);
+                module.insert_type(operand(0)?, Type::Pointer)?;
+            }
+            op::CONSTANT | op::SPEC_CONSTANT | op::CONSTANT_COMPOSITE | op::SPEC_CONSTANT_COMPOSITE => {
+                module.constants.insert(operand(1)?, operands[2..].to_vec());
+            }
+            op::VARIABLE => {
+                let storage_class = StorageClass::from(operand(2)?);
+                storage_classes.push(storage_class);
+                if storage_class == StorageClass::PushConstant {
+                    push_constant_types.push(operand(0)?);
+                }
+            }
+            op::DECORATE => match operand(1)? {
+                DECORATION_ARRAY_STRIDE => {
+                    module.array_strides.insert(operand(0)?, operand(2)?);
+                }
+                DECORATION_BUILT_IN if operand(2)? == BUILT_IN_WORKGROUP_SIZE => {
+                    workgroup_size_constant = Some(operand(0)?);
+                }
+                _ => {}
+            },
+            op::MEMBER_DECORATE => match operand(2)? {
+                DECORATION_OFFSET => {
+                    module.member_offsets.insert((operand(0)?, operand(1)?), operand(3)?);
+                }
+                DECORATION_MATRIX_STRIDE => {
+                    module.member_matrix_strides.insert((operand(0)?, operand(1)?), operand(3)?);
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    // A constant decorated as the workgroup size builtin overrides the execution modes.
+    let workgroup_size = workgroup_size_constant
+        .and_then(|id| module.constants.get(&id))
+        .map(|components| components.iter().map(|&id| module.constant(id).unwrap_or(1)).collect::<Vec<_>>())
+        .and_then(|components| <[u32; 3]>::try_from(components).ok());
+    for (entry_point, id) in entry_points.iter_mut().zip(entry_point_ids) {
+        if matches!(entry_point.stage, Some(ShaderStage::Compute | ShaderStage::Task | ShaderStage::Mesh)) {
+            let local_size_id = local_size_ids
+                .get(&id)
+                .map(|components: &[u32; 3]| components.map(|id| module.constant(id).unwrap_or(1)));
+            entry_point.workgroup_size = workgroup_size
+                .or(local_size_id)
+                .or_else(|| local_sizes.get(&id).copied());
+        }
+    }
+
+    let mut push_constant_size = 0;
+    for block in push_constant_types.iter().filter_map(|pointer| pointee_types.get(pointer)) {
+        push_constant_size = push_constant_size.max(module.size_of(*block, None)?);
+    }
+
+    storage_classes.sort();
+    storage_classes.dedup();
+    Ok(ShaderReflection {
+        entry_points,
+        push_constant_size,
+        storage_classes,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn instruction(opcode: u16, operands: &[u32]) -> Vec<u32> {
+        let mut words = vec![((operands.len() as u32 + 1) << 16) | opcode as u32];
+        words.extend_from_slice(operands);
+        words
+    }
+
+    fn string(string: &str) -> Vec<u32> {
+        let mut bytes = string.as_bytes().to_vec();
+        bytes.resize(string.len() / 4 * 4 + 4, 0);
+        bytes.chunks(4).map(|word| u32::from_le_bytes(word.try_into().unwrap())).collect()
+    }
+
+    // A compute shader `main` with a local size of 8x4x1 and the push constant block
+    // `{ uint a; vec4 b; uint64_t address; }` with std430 offsets 0, 16 and 32.
+    pub(crate) fn compute_shader() -> Vec<u32> {
+        let mut words = vec![0x0723_0203, 0x0001_0500, 0, 20, 0];
+        words.extend(instruction(op::ENTRY_POINT, &[[5].as_slice(), &[1], &string("main")].concat()));
+        words.extend(instruction(op::EXECUTION_MODE, &[1, EXECUTION_MODE_LOCAL_SIZE, 8, 4, 1]));
+        words.extend(instruction(op::MEMBER_DECORATE, &[6, 0, DECORATION_OFFSET, 0]));
+        words.extend(instruction(op::MEMBER_DECORATE, &[6, 1, DECORATION_OFFSET, 16]));
+        words.extend(instruction(op::MEMBER_DECORATE, &[6, 2, DECORATION_OFFSET, 32]));
+        words.extend(instruction(op::TYPE_INT, &[2, 32, 0]));
+        words.extend(instruction(op::TYPE_FLOAT, &[3, 32]));
+        words.extend(instruction(op::TYPE_VECTOR, &[4, 3, 4]));
+        words.extend(instruction(op::TYPE_INT, &[5, 64, 0]));
+        words.extend(instruction(op::TYPE_STRUCT, &[6, 2, 4, 5]));
+        words.extend(instruction(op::TYPE_POINTER, &[7, 9, 6]));
+        words.extend(instruction(op::VARIABLE, &[7, 8, 9]));
+        words
+    }
+
+    // A shader with an empty entry point `main` for `stage`, for tests that only look at stages.
+    pub(crate) fn stage_shader(stage: ShaderStage) -> Vec<u32> {
+        let execution_model = match stage {
+            ShaderStage::Vertex => 0,
+            ShaderStage::TesselationControl => 1,
+            ShaderStage::TesselationEvaluation => 2,
+            ShaderStage::Fragment => 4,
+            ShaderStage::Compute => 5,
+            ShaderStage::Task => 5364,
+            ShaderStage::Mesh => 5365,
+        };
+        let mut words = vec![0x0723_0203, 0x0001_0500, 0, 2, 0];
+        words.extend(instruction(op::ENTRY_POINT, &[[execution_model].as_slice(), &[1], &string("main")].concat()));
+        words
+    }
+
+    #[test]
+    fn reflects_compute_shader() {
+        let reflection = reflect(&compute_shader()).unwrap();
+        assert_eq!(
+            reflection.entry_points,
+            vec![EntryPoint {
+                name: "main".to_owned(),
+                stage: Some(ShaderStage::Compute),
+                workgroup_size: Some([8, 4, 1]),
+            }]
+        );
+        assert_eq!(reflection.push_constant_size, 40);
+        assert_eq!(reflection.storage_classes, vec![StorageClass::PushConstant]);
+    }
+
+    #[test]
+    fn reflects_stages() {
+        for stage in [
+            ShaderStage::Vertex,
+            ShaderStage::TesselationControl,
+            ShaderStage::TesselationEvaluation,
+            ShaderStage::Fragment,
+            ShaderStage::Compute,
+            ShaderStage::Task,
+            ShaderStage::Mesh,
+        ] {
+            let reflection = reflect(&stage_shader(stage)).unwrap();
+            assert_eq!(reflection.entry_points[0].stage, Some(stage));
+            assert_eq!(reflection.entry_points[0].workgroup_size, None);
+        }
+    }
+
+    #[test]
+    fn workgroup_size_builtin_overrides_local_size() {
+        let mut words = compute_shader();
+        words.extend(instruction(op::DECORATE, &[13, DECORATION_BUILT_IN, BUILT_IN_WORKGROUP_SIZE]));
+        words.extend(instruction(op::CONSTANT, &[2, 10, 64]));
+        words.extend(instruction(op::CONSTANT, &[2, 11, 1]));
+        words.extend(instruction(op::TYPE_VECTOR, &[12, 2, 3]));
+        words.extend(instruction(op::CONSTANT_COMPOSITE, &[12, 13, 10, 11, 11]));
+        let reflection = reflect(&words).unwrap();
+        assert_eq!(reflection.entry_points[0].workgroup_size, Some([64, 1, 1]));
+    }
+
+    #[test]
+    fn reads_local_size_ids() {
+        let mut words = compute_shader();
+        words.extend(instruction(op::EXECUTION_MODE_ID, &[1, EXECUTION_MODE_LOCAL_SIZE_ID, 10, 11, 11]));
+        words.extend(instruction(op::CONSTANT, &[2, 10, 32]));
+        words.extend(instruction(op::CONSTANT, &[2, 11, 2]));
+        let reflection = reflect(&words).unwrap();
+        assert_eq!(reflection.entry_points[0].workgroup_size, Some([32, 2, 2]));
+    }
+
+    #[test]
+    fn rejects_recursive_and_oversized_types() {
+        let mut words = compute_shader();
+        words.extend(instruction(op::TYPE_STRUCT, &[14, 14]));
+        assert!(matches!(reflect(&words), Err(ShaderError::Malformed(_))));
+
+        let mut words = compute_shader();
+        words.extend(instruction(op::CONSTANT, &[2, 10, u32::MAX]));
+        words.extend(instruction(op::TYPE_ARRAY, &[11, 4, 10]));
+        words.extend(instruction(op::TYPE_STRUCT, &[12, 11]));
+        words.extend(instruction(op::TYPE_POINTER, &[13, 9, 12]));
+        words.extend(instruction(op::VARIABLE, &[13, 15, 9]));
+        assert!(matches!(reflect(&words), Err(ShaderError::Malformed(_))));
+    }
+
+    #[test]
+    fn rejects_truncated_instructions() {
+        let mut words = compute_shader();
+        words.pop();
+        assert!(matches!(reflect(&words), Err(ShaderError::Malformed(_))));
+    }
+}
@@ -0,0 +1,170 @@
+use std::path::{Path, PathBuf};
+
+use crate::pipeline::ShaderInfo;
+
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+// Magic number, version, generator, bound and schema.
+const HEADER_WORDS: usize = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ShaderStage {
+    Vertex,
+    TesselationControl,
+    TesselationEvaluation,
+    Fragment,
+    Compute,
+    Task,
+    Mesh,
+}
+
+/// A compiler diagnostic. `line` and `column` start at 1, they are 0 when the location is unknown.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CompileMessage {
+    pub path: PathBuf,
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+}
+
+impl std::fmt::Display for CompileMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.path.display(), self.line, self.column, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Io(std::io::Error),
+    /// The byte length is not a multiple of 4.
+    NotWordAligned(usize),
+    /// Shorter than the SPIR-V header.
+    TooShort(usize),
+    /// The first word is not the SPIR-V magic number in either byte order.
+    InvalidMagic(u32),
+    /// The instruction stream could not be reflected.
+    Malformed(&'static str),
+    Compile(Vec<CompileMessage>),
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShaderError::Io(error) => write!(f, "could not read the shader: {}", error),
+            ShaderError::NotWordAligned(len) => write!(f, "SPIR-V length of {} bytes is not a multiple of 4", len),
+            ShaderError::TooShort(len) => write!(f, "SPIR-V of {} bytes is shorter than its header", len),
+            ShaderError::InvalidMagic(magic) => write!(f, "invalid SPIR-V magic number {:#010x}", magic),
+            ShaderError::Malformed(reason) => write!(f, "malformed SPIR-V: {}", reason),
+            ShaderError::Compile(messages) => {
+                write!(f, "shader compilation failed")?;
+                for message in messages {
+                    write!(f, "\n{}", message)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {
+    fn source(&self) -> std::option::Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ShaderError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ShaderError {
+    fn from(error: std::io::Error) -> Self {
+        ShaderError::Io(error)
+    }
+}
+
+/// Validated SPIR-V byte code in native byte order.
+///
+/// Pipeline infos borrow it through [`ShaderInfo`] until the pipeline is created, so the byte code
+/// can not be dropped while an info still points at it:
+///
+/// ```compile_fail
+/// # use daxa_rs::*;
+/// let shader_info = {
+///     let spirv = SpirV::from_file("shaders/blur.spv").unwrap();
+///     spirv.shader_info("main")
+/// };
+/// let info = ComputePipelineInfo::new(shader_info);
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SpirV {
+    words: Vec<u32>,
+}
+
+impl SpirV {
+    pub fn from_words(words: &[u32]) -> std::result::Result<Self, ShaderError> {
+        Self::new(words.to_vec())
+    }
+
+    /// Accepts little and big endian SPIR-V, the bytes do not need to be aligned.
+    pub fn from_bytes(bytes: &[u8]) -> std::result::Result<Self, ShaderError> {
+        if !bytes.len().is_multiple_of(4) {
+            return Err(ShaderError::NotWordAligned(bytes.len()));
+        }
+        let words = bytes
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        Self::new(words)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> std::result::Result<Self, ShaderError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    fn new(mut words: Vec<u32>) -> std::result::Result<Self, ShaderError> {
+        if words.len() < HEADER_WORDS {
+            return Err(ShaderError::TooShort(words.len() * 4));
+        }
+        match words[0] {
+            SPIRV_MAGIC => {}
+            magic if magic == SPIRV_MAGIC.swap_bytes() => {
+                words.iter_mut().for_each(|word| *word = word.swap_bytes());
+            }
+            magic => return Err(ShaderError::InvalidMagic(magic)),
+        }
+        Ok(Self { words })
+    }
+
+    pub fn words(&self) -> &[u32] {
+        &self.words
+    }
+
+    /// A stage of a pipeline that starts in the function `entry_point`.
+    pub fn shader_info(&self, entry_point: &str) -> ShaderInfo<'_> {
+        ShaderInfo::new(self, entry_point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: [u32; HEADER_WORDS] = [SPIRV_MAGIC, 0x0001_0000, 0, 1, 0];
+
+    #[test]
+    fn reads_both_byte_orders() {
+        let little: Vec<u8> = HEADER.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let big: Vec<u8> = HEADER.iter().flat_map(|word| word.to_be_bytes()).collect();
+        assert_eq!(SpirV::from_bytes(&little).unwrap().words(), &HEADER);
+        assert_eq!(SpirV::from_bytes(&big).unwrap().words(), &HEADER);
+        // Unaligned start.
+        let mut shifted = vec![0u8];
+        shifted.extend_from_slice(&little);
+        assert_eq!(SpirV::from_bytes(&shifted[1..]).unwrap().words(), &HEADER);
+    }
+
+    #[test]
+    fn rejects_invalid_byte_code() {
+        assert!(matches!(SpirV::from_bytes(&[0; 21]), Err(ShaderError::NotWordAligned(21))));
+        assert!(matches!(SpirV::from_words(&HEADER[..2]), Err(ShaderError::TooShort(8))));
+        assert!(matches!(SpirV::from_words(&[0; HEADER_WORDS]), Err(ShaderError::InvalidMagic(0))));
+    }
+}
@@ -0,0 +1,183 @@
+use std::path::Path;
+
+use crate::shader::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShaderLanguage {
+    Glsl,
+    Wgsl,
+}
+
+pub struct ShaderCompileInfo<'a> {
+    pub source: &'a str,
+    /// Only used to point compile errors at the right file.
+    pub path: &'a Path,
+    pub language: ShaderLanguage,
+    pub stage: ShaderStage,
+    /// Always `main` for GLSL. A WGSL source may contain several entry points, only this one ends
+    /// up in the byte code.
+    pub entry_point: &'a str,
+    /// Preprocessor definitions as `(name, value)` pairs. WGSL has no preprocessor, so they are
+    /// only supported for GLSL.
+    pub defines: &'a [(&'a str, &'a str)],
+}
+
+/// Compiles GLSL or WGSL source to SPIR-V with naga, in process.
+pub fn compile_shader(info: &ShaderCompileInfo) -> std::result::Result<SpirV, ShaderError> {
+    let message = |span: std::option::Option<naga::Span>, message: String| {
+        let location = span.filter(|span| span.is_defined()).map(|span| span.location(info.source));
+        CompileMessage {
+            path: info.path.to_owned(),
+            line: location.map_or(0, |location| location.line_number),
+            column: location.map_or(0, |location| location.line_position),
+            message,
+        }
+    };
+
+    let stage = match info.stage {
+        ShaderStage::Vertex => naga::ShaderStage::Vertex,
+        ShaderStage::Fragment => naga::ShaderStage::Fragment,
+        ShaderStage::Compute => naga::ShaderStage::Compute,
+        stage => {
+            return Err(ShaderError::Compile(vec![message(
+                None,
+                format!("naga can not compile {:?} shaders", stage),
+            )]))
+        }
+    };
+
+    let module = match info.language {
+        ShaderLanguage::Glsl => {
+            let mut options = naga::front::glsl::Options::from(stage);
+            options.defines = info
+                .defines
+                .iter()
+                .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+                .collect();
+            naga::front::glsl::Frontend::default()
+                .parse(&options, info.source)
+                .map_err(|errors| {
+                    ShaderError::Compile(
+                        errors
+                            .into_iter()
+                            .map(|error| message(Some(error.meta), error.kind.to_string()))
+                            .collect(),
+                    )
+                })?
+        }
+        ShaderLanguage::Wgsl => {
+            if !info.defines.is_empty() {
+                return Err(ShaderError::Compile(vec![message(
+                    None,
+                    "WGSL does not support preprocessor defines".to_owned(),
+                )]));
+            }
+            naga::front::wgsl::parse_str(info.source).map_err(|error| {
+                let span = error.labels().next().map(|(span, _)| span);
+                ShaderError::Compile(vec![message(span, error.to_string())])
+            })?
+        }
+    };
+
+    let module_info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+        .validate(&module)
+        .map_err(|error| {
+            let span = error.spans().next().map(|&(span, _)| span);
+            ShaderError::Compile(vec![message(span, error_chain(error.as_inner()))])
+        })?;
+
+    // naga's defaults target wgpu: they flip y in vertex outputs, clamp fragment depth and emit
+    // SPIR-V 1.0. daxa hands the byte code to Vulkan 1.3 as is.
+    let mut flags = naga::back::spv::WriterFlags::LABEL_VARYINGS;
+    if cfg!(debug_assertions) {
+        flags |= naga::back::spv::WriterFlags::DEBUG;
+    }
+    let options = naga::back::spv::Options {
+        lang_version: (1, 3),
+        flags,
+        ..Default::default()
+    };
+    let words = naga::back::spv::write_vec(
+        &module,
+        &module_info,
+        &options,
+        Some(&naga::back::spv::PipelineOptions {
+            shader_stage: stage,
+            entry_point: info.entry_point.to_owned(),
+        }),
+    )
+    .map_err(|error| ShaderError::Compile(vec![message(None, error.to_string())]))?;
+    SpirV::from_words(&words)
+}
+
+// Validation errors only name the invalid function at the top level, the cause is in the sources.
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message += &format!(": {}", error);
+        source = error.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info<'a>(source: &'a str, entry_point: &'a str) -> ShaderCompileInfo<'a> {
+        ShaderCompileInfo {
+            source,
+            path: Path::new("test.wgsl"),
+            language: ShaderLanguage::Wgsl,
+            stage: ShaderStage::Compute,
+            entry_point,
+            defines: &[],
+        }
+    }
+
+    #[test]
+    fn compiles_wgsl() {
+        let spirv = compile_shader(&info("@compute @workgroup_size(8)\nfn cs() {}", "cs")).unwrap();
+        assert_eq!(spirv.words()[0], 0x0723_0203);
+    }
+
+    #[test]
+    fn compiles_glsl_with_defines() {
+        let source = "#version 450\nlayout(local_size_x = SIZE, local_size_y = 2) in;\nvoid main() {}";
+        let info = |defines: &'static [(&'static str, &'static str)]| ShaderCompileInfo {
+            source,
+            path: Path::new("test.comp"),
+            language: ShaderLanguage::Glsl,
+            stage: ShaderStage::Compute,
+            entry_point: "main",
+            defines,
+        };
+        let spirv = compile_shader(&info(&[("SIZE", "16")])).unwrap();
+        assert_eq!(spirv.words()[1], 0x0001_0300);
+        let reflection = spirv.reflect().unwrap();
+        let entry_point = reflection.entry_point("main").unwrap();
+        assert_eq!(entry_point.stage, Some(ShaderStage::Compute));
+        assert_eq!(entry_point.workgroup_size, Some([16, 2, 1]));
+
+        assert!(matches!(compile_shader(&info(&[])), Err(ShaderError::Compile(_))));
+    }
+
+    #[test]
+    fn rejects_defines_in_wgsl() {
+        let wgsl = ShaderCompileInfo {
+            defines: &[("SIZE", "16")],
+            ..info("@compute @workgroup_size(8)\nfn cs() {}", "cs")
+        };
+        assert!(matches!(compile_shader(&wgsl), Err(ShaderError::Compile(_))));
+    }
+
+    #[test]
+    fn reports_error_location() {
+        let Err(ShaderError::Compile(messages)) = compile_shader(&info("@compute @workgroup_size(8)\nfn cs() {\n  let x = ;\n}", "cs")) else {
+            panic!("expected a compile error");
+        };
+        assert_eq!((messages[0].line, messages[0].column), (3, 11));
+        assert_eq!(messages[0].path, Path::new("test.wgsl"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::backend as daxa_sys;
 use crate::types::{self, Swapchain};
 
 type NativeWindowHandle = daxa_sys::daxa_NativeWindowHandle;
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+
+use crate::backend as daxa_sys;
+use crate::command_recorder::*;
+use crate::types::*;
+
+/// Declares how a recorded command touches a resource.
+#[derive(Clone, Copy)]
+pub enum ResourceUse {
+    Buffer {
+        id: BufferId,
+        access: Access,
+    },
+    Image {
+        id: ImageId,
+        slice: ImageMipArraySlice,
+        access: Access,
+        layout: ImageLayout,
+    },
+}
+
+impl ResourceUse {
+    pub fn buffer(id: BufferId, access: Access) -> Self {
+        Self::Buffer { id, access }
+    }
+
+    pub fn image(id: ImageId, slice: ImageMipArraySlice, access: Access, layout: ImageLayout) -> Self {
+        Self::Image {
+            id,
+            slice,
+            access,
+            layout,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+struct AccessState {
+    last_write: Access,
+    // Stages that read since the last write and are already ordered after it.
+    reads_since_write: PipelineStageFlags,
+}
+
+impl AccessState {
+    /// Records `access` and returns what it has to wait on, if anything.
+    fn access(&mut self, access: Access) -> std::option::Option<Access> {
+        if access.is_write() {
+            let src = Access::new(self.last_write.stages | self.reads_since_write, self.last_write.ty);
+            *self = AccessState {
+                last_write: access,
+                reads_since_write: PipelineStageFlags::empty(),
+            };
+            (!src.stages.is_empty()).then_some(src)
+        } else {
+            let already_synced = self.reads_since_write.contains(access.stages);
+            self.reads_since_write |= access.stages;
+            (!self.last_write.stages.is_empty() && !already_synced).then_some(self.last_write)
+        }
+    }
+
+    /// Records a layout transition into `access` and returns what the transition has to wait on.
+    fn transition(&mut self, access: Access) -> Access {
+        let src = Access::new(self.last_write.stages | self.reads_since_write, self.last_write.ty);
+        // The transition itself is a write, later accesses outside of its destination stages
+        // still have to be ordered after it.
+        *self = if access.is_write() {
+            AccessState {
+                last_write: access,
+                reads_since_write: PipelineStageFlags::empty(),
+            }
+        } else {
+            AccessState {
+                last_write: Access::new(access.stages, AccessTypeFlags::NONE),
+                reads_since_write: access.stages,
+            }
+        };
+        src
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct SubresourceState {
+    layout: ImageLayout,
+    access: AccessState,
+}
+
+impl Default for SubresourceState {
+    fn default() -> Self {
+        Self {
+            layout: ImageLayout::Undefined,
+            access: Default::default(),
+        }
+    }
+}
+
+/// A barrier the tracked recorder inserted on its own.
+#[derive(Clone, Copy, Debug)]
+pub enum InsertedBarrier {
+    Memory(MemoryBarrierInfo),
+    Image(ImageMemoryBarrierInfo),
+}
+
+impl fmt::Display for InsertedBarrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InsertedBarrier::Memory(info) => write!(
+                f,
+                "memory barrier: {:?} {:?} -> {:?} {:?}",
+                info.src_access.stages, info.src_access.ty, info.dst_access.stages, info.dst_access.ty
+            ),
+            InsertedBarrier::Image(info) => write!(
+                f,
+                "image barrier: image {} mips {}..{} layers {}..{}: {:?} {:?} {:?} -> {:?} {:?} {:?}",
+                info.image_id.value,
+                info.image_slice.base_mip_level,
+                info.image_slice.base_mip_level + info.image_slice.level_count,
+                info.image_slice.base_array_layer,
+                info.image_slice.base_array_layer + info.image_slice.layer_count,
+                info.src_layout,
+                info.src_access.stages,
+                info.src_access.ty,
+                info.dst_layout,
+                info.dst_access.stages,
+                info.dst_access.ty
+            ),
+        }
+    }
+}
+
+/// A [`CommandRecorder`] that remembers the last access and layout of every buffer and image
+/// subresource it has seen, and inserts the barriers and layout transitions each command needs.
+pub struct TrackedCommandRecorder {
+    recorder: CommandRecorder,
+    buffers: HashMap<u64, AccessState>,
+    images: HashMap<u64, HashMap<(u32, u32), SubresourceState>>,
+    command_index: usize,
+    inserted: Vec<(usize, InsertedBarrier)>,
+}
+
+impl TrackedCommandRecorder {
+    pub fn new(recorder: CommandRecorder) -> Self {
+        Self {
+            recorder,
+            buffers: HashMap::new(),
+            images: HashMap::new(),
+            command_index: 0,
+            inserted: Vec::new(),
+        }
+    }
+
+    pub fn recorder(&self) -> &CommandRecorder {
+        &self.recorder
+    }
+
+    pub fn into_inner(self) -> CommandRecorder {
+        self.recorder
+    }
+
+    /// Tells the tracker which layout an image is in before this recorder touches it,
+    /// e.g. for images that were last used by another recorder.
+    pub fn assume_image_layout(&mut self, image: ImageId, slice: ImageMipArraySlice, layout: ImageLayout) {
+        let subresources = self.images.entry(image.value).or_default();
+        for mip in slice.base_mip_level..slice.base_mip_level + slice.level_count {
+            for layer in slice.base_array_layer..slice.base_array_layer + slice.layer_count {
+                subresources.insert(
+                    (mip, layer),
+                    SubresourceState {
+                        layout,
+                        access: Default::default(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Inserts the barriers needed for `uses`, then records the command(s) in `record`.
+    ///
+    /// The tracked states only change once all barriers were recorded, so a failed call leaves the
+    /// tracker as it was.
+    pub fn record<R>(
+        &mut self,
+        uses: &[ResourceUse],
+        record: impl FnOnce(&CommandRecorder) -> R,
+    ) -> std::result::Result<R, crate::types::Result> {
+        let mut buffers: HashMap<u64, AccessState> = HashMap::new();
+        let mut images: HashMap<u64, HashMap<(u32, u32), SubresourceState>> = HashMap::new();
+        let mut memory_barrier: std::option::Option<MemoryBarrierInfo> = None;
+        let mut image_barriers = Vec::new();
+
+        for resource_use in uses {
+            match *resource_use {
+                ResourceUse::Buffer { id, access } => {
+                    let state = buffers
+                        .entry(id.value)
+                        .or_insert_with(|| self.buffers.get(&id.value).copied().unwrap_or_default());
+                    if let Some(src_access) = state.access(access) {
+                        // daxa only has global memory barriers, so all buffer hazards of one
+                        // command collapse into a single barrier.
+                        let barrier = memory_barrier.get_or_insert(Default::default());
+                        barrier.src_access |= src_access;
+                        barrier.dst_access |= access;
+                    }
+                }
+                ResourceUse::Image {
+                    id,
+                    slice,
+                    access,
+                    layout,
+                } => {
+                    let subresources = images
+                        .entry(id.value)
+                        .or_insert_with(|| self.images.get(&id.value).cloned().unwrap_or_default());
+                    track_image(subresources, id, slice, access, layout, &mut image_barriers);
+                }
+            }
+        }
+
+        // daxa_cmd_pipeline_barrier returns nothing, only image transitions can fail.
+        if let Some(barrier) = memory_barrier {
+            self.recorder.pipeline_barrier(&barrier);
+        }
+        for barrier in &image_barriers {
+            match unsafe {
+                mem::transmute::<daxa_sys::daxa_Result, Result>(self.recorder.pipeline_barrier_image_transition(barrier))
+            } {
+                Result::Success => {}
+                error => return Err(error),
+            }
+        }
+
+        self.buffers.extend(buffers);
+        self.images.extend(images);
+        let inserted = memory_barrier
+            .map(InsertedBarrier::Memory)
+            .into_iter()
+            .chain(image_barriers.into_iter().map(InsertedBarrier::Image));
+        self.inserted.extend(inserted.map(|barrier| (self.command_index, barrier)));
+        self.command_index += 1;
+        Ok(record(&self.recorder))
+    }
+
+    /// All barriers inserted so far, tagged with the index of the command they were inserted before.
+    pub fn inserted_barriers(&self) -> &[(usize, InsertedBarrier)] {
+        &self.inserted
+    }
+
+    /// Human readable listing of [`Self::inserted_barriers`], one barrier per line.
+    pub fn dump_inserted_barriers(&self) -> String {
+        self.inserted
+            .iter()
+            .map(|(command_index, barrier)| format!("#{command_index} {barrier}\n"))
+            .collect()
+    }
+
+    pub fn complete_current_commands(&self) -> std::result::Result<ExecutableCommandList, CommandError> {
+        self.recorder.complete_current_commands()
+    }
+}
+
+fn track_image(
+    subresources: &mut HashMap<(u32, u32), SubresourceState>,
+    image: ImageId,
+    slice: ImageMipArraySlice,
+    access: Access,
+    layout: ImageLayout,
+    barriers: &mut Vec<ImageMemoryBarrierInfo>,
+) {
+    for mip in slice.base_mip_level..slice.base_mip_level + slice.level_count {
+        for layer in slice.base_array_layer..slice.base_array_layer + slice.layer_count {
+            let state = subresources.entry((mip, layer)).or_default();
+            let src_layout = state.layout;
+            let src_access = if src_layout != layout {
+                state.layout = layout;
+                Some(state.access.transition(access))
+            } else {
+                state.access.access(access)
+            };
+            let Some(src_access) = src_access else {
+                continue;
+            };
+            let barrier = ImageMemoryBarrierInfo {
+                src_access,
+                dst_access: access,
+                src_layout,
+                dst_layout: layout,
+                image_slice: ImageMipArraySlice {
+                    base_mip_level: mip,
+                    level_count: 1,
+                    base_array_layer: layer,
+                    layer_count: 1,
+                },
+                image_id: image,
+            };
+            push_merged(barriers, barrier);
+        }
+        merge_last_mip(barriers);
+    }
+}
+
+fn same_transition(a: &ImageMemoryBarrierInfo, b: &ImageMemoryBarrierInfo) -> bool {
+    a.image_id.value == b.image_id.value
+        && a.src_access == b.src_access
+        && a.dst_access == b.dst_access
+        && a.src_layout == b.src_layout
+        && a.dst_layout == b.dst_layout
+}
+
+// Subresources are visited layer by layer within each mip, so a new barrier can only extend the
+// last one by one more layer of the same mip.
+fn push_merged(barriers: &mut Vec<ImageMemoryBarrierInfo>, barrier: ImageMemoryBarrierInfo) {
+    if let Some(last) = barriers.last_mut() {
+        let last_slice = last.image_slice;
+        let slice = barrier.image_slice;
+        if same_transition(last, &barrier)
+            && last_slice.level_count == 1
+            && last_slice.base_mip_level == slice.base_mip_level
+            && last_slice.base_array_layer + last_slice.layer_count == slice.base_array_layer
+        {
+            last.image_slice.layer_count += 1;
+            return;
+        }
+    }
+    barriers.push(barrier);
+}
+
+// Once a mip is done, its layer run may continue the barrier of the previous mips.
+fn merge_last_mip(barriers: &mut Vec<ImageMemoryBarrierInfo>) {
+    let [.., previous, last] = barriers.as_mut_slice() else {
+        return;
+    };
+    let (previous_slice, last_slice) = (previous.image_slice, last.image_slice);
+    if same_transition(previous, last)
+        && previous_slice.base_mip_level + previous_slice.level_count == last_slice.base_mip_level
+        && last_slice.level_count == 1
+        && previous_slice.base_array_layer == last_slice.base_array_layer
+        && previous_slice.layer_count == last_slice.layer_count
+    {
+        previous.image_slice.level_count += 1;
+        barriers.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::null_backend::tests::device;
+
+    const COMPUTE_READ: Access = Access::COMPUTE_SHADER_READ;
+    const COMPUTE_WRITE: Access = Access::COMPUTE_SHADER_WRITE;
+    const TRANSFER_READ: Access = Access::TRANSFER_READ;
+    const TRANSFER_WRITE: Access = Access::TRANSFER_WRITE;
+
+    fn slice(base_mip_level: u32, level_count: u32, base_array_layer: u32, layer_count: u32) -> ImageMipArraySlice {
+        ImageMipArraySlice {
+            base_mip_level,
+            level_count,
+            base_array_layer,
+            layer_count,
+        }
+    }
+
+    fn tracked_recorder() -> TrackedCommandRecorder {
+        TrackedCommandRecorder::new(device().create_command_recorder(&Default::default()).unwrap())
+    }
+
+    #[test]
+    fn read_after_read_needs_no_barrier() {
+        let mut state = AccessState::default();
+        assert_eq!(state.access(COMPUTE_READ), None);
+        assert_eq!(state.access(TRANSFER_READ), None);
+    }
+
+    #[test]
+    fn read_after_write_waits_once_per_stage() {
+        let mut state = AccessState::default();
+        assert_eq!(state.access(COMPUTE_WRITE), None);
+        assert_eq!(state.access(TRANSFER_READ), Some(COMPUTE_WRITE));
+        assert_eq!(state.access(TRANSFER_READ), None);
+        assert_eq!(state.access(COMPUTE_READ), Some(COMPUTE_WRITE));
+    }
+
+    #[test]
+    fn write_after_read_waits_on_readers() {
+        let mut state = AccessState::default();
+        state.access(COMPUTE_WRITE);
+        state.access(TRANSFER_READ);
+        let src = state.access(COMPUTE_WRITE).unwrap();
+        assert_eq!(
+            src.stages,
+            PipelineStageFlags::COMPUTE_SHADER_BIT | PipelineStageFlags::TRANSFER_BIT
+        );
+    }
+
+    #[test]
+    fn merges_subresource_barriers_per_mip() {
+        let barrier = |mip, layer| ImageMemoryBarrierInfo {
+            src_access: Access::NONE,
+            dst_access: TRANSFER_WRITE,
+            src_layout: ImageLayout::Undefined,
+            dst_layout: ImageLayout::TransferDstOptimal,
+            image_slice: slice(mip, 1, layer, 1),
+            image_id: ImageId { value: 1 },
+        };
+        let mut barriers = Vec::new();
+        for mip in 0..3 {
+            for layer in 2..4 {
+                push_merged(&mut barriers, barrier(mip, layer));
+            }
+            merge_last_mip(&mut barriers);
+        }
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].image_slice, slice(0, 3, 2, 2));
+
+        // A mip covering fewer layers can not extend the run.
+        push_merged(&mut barriers, barrier(3, 2));
+        merge_last_mip(&mut barriers);
+        assert_eq!(barriers.len(), 2);
+        assert_eq!(barriers[0].image_slice, slice(0, 3, 2, 2));
+        assert_eq!(barriers[1].image_slice, slice(3, 1, 2, 1));
+    }
+
+    #[test]
+    fn transitions_image_layouts() {
+        let mut recorder = tracked_recorder();
+        let image = ImageId { value: 1 };
+        let write = ResourceUse::image(image, slice(0, 2, 0, 2), TRANSFER_WRITE, ImageLayout::TransferDstOptimal);
+        let read = ResourceUse::image(image, slice(0, 1, 0, 2), COMPUTE_READ, ImageLayout::ReadOnlyOptimal);
+        recorder.record(&[write], |_| ()).unwrap();
+        recorder.record(&[read], |_| ()).unwrap();
+        recorder.record(&[read], |_| ()).unwrap();
+
+        let barriers: Vec<_> = recorder
+            .inserted_barriers()
+            .iter()
+            .map(|&(command_index, barrier)| match barrier {
+                InsertedBarrier::Image(barrier) => (command_index, barrier),
+                InsertedBarrier::Memory(_) => panic!("unexpected memory barrier"),
+            })
+            .collect();
+        assert_eq!(barriers.len(), 2);
+        let (command_index, undefined_to_write) = barriers[0];
+        assert_eq!(command_index, 0);
+        assert_eq!(undefined_to_write.src_layout, ImageLayout::Undefined);
+        assert_eq!(undefined_to_write.dst_layout, ImageLayout::TransferDstOptimal);
+        assert_eq!(undefined_to_write.image_slice, slice(0, 2, 0, 2));
+        let (command_index, write_to_read) = barriers[1];
+        assert_eq!(command_index, 1);
+        assert_eq!(write_to_read.src_access, TRANSFER_WRITE);
+        assert_eq!(write_to_read.src_layout, ImageLayout::TransferDstOptimal);
+        assert_eq!(write_to_read.dst_layout, ImageLayout::ReadOnlyOptimal);
+        assert_eq!(write_to_read.image_slice, slice(0, 1, 0, 2));
+    }
+
+    #[test]
+    fn collapses_buffer_hazards_into_one_memory_barrier() {
+        let mut recorder = tracked_recorder();
+        let (a, b) = (BufferId { value: 1 }, BufferId { value: 2 });
+        recorder
+            .record(&[ResourceUse::buffer(a, COMPUTE_WRITE), ResourceUse::buffer(b, TRANSFER_WRITE)], |_| ())
+            .unwrap();
+        assert!(recorder.inserted_barriers().is_empty());
+
+        recorder
+            .record(&[ResourceUse::buffer(a, TRANSFER_READ), ResourceUse::buffer(b, COMPUTE_READ)], |_| ())
+            .unwrap();
+        let [(1, InsertedBarrier::Memory(barrier))] = recorder.inserted_barriers() else {
+            panic!("expected a single memory barrier, got {}", recorder.dump_inserted_barriers());
+        };
+        assert_eq!(barrier.src_access, COMPUTE_WRITE | TRANSFER_WRITE);
+        assert_eq!(barrier.dst_access, TRANSFER_READ | COMPUTE_READ);
+    }
+}
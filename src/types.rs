@@ -1,16 +1,21 @@
 use bitflags::bitflags;
 use std::{marker::PhantomData, mem, os};
 
+use crate::backend as daxa_sys;
+use crate::command_recorder::ExecutableCommandList;
 use crate::device::{Device, DeviceType};
 
 #[derive(Debug)]
 #[repr(i32)]
 pub enum Result {
     Success = daxa_sys::daxa_Result_DAXA_RESULT_SUCCESS,
+    Timeout = daxa_sys::daxa_Result_DAXA_RESULT_TIMEOUT,
+    ErrorUnknown = daxa_sys::daxa_Result_DAXA_RESULT_ERROR_UNKNOWN,
     MissingExtension = daxa_sys::daxa_Result_DAXA_RESULT_MISSING_EXTENSION,
 }
 
 #[repr(i32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ImageLayout {
     Undefined = daxa_sys::daxa_ImageLayout_DAXA_IMAGE_LAYOUT_UNDEFINED,
     General = daxa_sys::daxa_ImageLayout_DAXA_IMAGE_LAYOUT_GENERAL,
@@ -24,34 +29,34 @@ pub enum ImageLayout {
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct Vec2<T> {
-    x: T,
-    y: T,
+    pub x: T,
+    pub y: T,
 }
 
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct Vec3<T> {
-    x: T,
-    y: T,
-    z: T,
+    pub x: T,
+    pub y: T,
+    pub z: T,
 }
 
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct Vec4<T> {
-    x: T,
-    y: T,
-    z: T,
-    w: T,
+    pub x: T,
+    pub y: T,
+    pub z: T,
+    pub w: T,
 }
 
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct ImageMipArraySlice {
-    base_mip_level: u32,
-    level_count: u32,
-    base_array_layer: u32,
-    layer_count: u32,
+    pub base_mip_level: u32,
+    pub level_count: u32,
+    pub base_array_layer: u32,
+    pub layer_count: u32,
 }
 #[repr(C)]
 #[derive(Clone, Copy)]
@@ -107,6 +112,122 @@ bitflags! {
     }
 }
 
+bitflags! {
+    #[derive(Default)]
+    pub struct AccessTypeFlags: u64 {
+        const NONE = 0;
+        const READ = daxa_sys::VK_ACCESS_2_MEMORY_READ_BIT;
+        const WRITE = daxa_sys::VK_ACCESS_2_MEMORY_WRITE_BIT;
+        const READ_WRITE = Self::READ.bits | Self::WRITE.bits;
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Access {
+    pub stages: PipelineStageFlags,
+    pub ty: AccessTypeFlags,
+}
+
+macro_rules! access_consts {
+    ($($name:ident => $stages:ident),* $(,)?) => {
+        paste::item! {
+            impl Access {
+                $(
+                    pub const [< $name _READ >]: Access = Access::new(PipelineStageFlags::$stages, AccessTypeFlags::READ);
+                    pub const [< $name _WRITE >]: Access = Access::new(PipelineStageFlags::$stages, AccessTypeFlags::WRITE);
+                    pub const [< $name _READ_WRITE >]: Access = Access::new(PipelineStageFlags::$stages, AccessTypeFlags::READ_WRITE);
+                )*
+            }
+        }
+    };
+}
+
+access_consts!(
+    TOP_OF_PIPE => TOP_OF_PIPE_BIT,
+    DRAW_INDIRECT => DRAW_INDIRECT_BIT,
+    VERTEX_SHADER => VERTEX_SHADER_BIT,
+    TESSELLATION_CONTROL_SHADER => TESSELLATION_CONTROL_SHADER_BIT,
+    TESSELLATION_EVALUATION_SHADER => TESSELLATION_EVALUATION_SHADER_BIT,
+    GEOMETRY_SHADER => GEOMETRY_SHADER_BIT,
+    FRAGMENT_SHADER => FRAGMENT_SHADER_BIT,
+    EARLY_FRAGMENT_TESTS => EARLY_FRAGMENT_TESTS_BIT,
+    LATE_FRAGMENT_TESTS => LATE_FRAGMENT_TESTS_BIT,
+    COLOR_ATTACHMENT => COLOR_ATTACHMENT_OUTPUT_BIT,
+    COMPUTE_SHADER => COMPUTE_SHADER_BIT,
+    TRANSFER => TRANSFER_BIT,
+    BOTTOM_OF_PIPE => BOTTOM_OF_PIPE_BIT,
+    HOST => HOST_BIT,
+    ALL_GRAPHICS => ALL_GRAPHICS_BIT,
+    TASK_SHADER => TASK_SHADER_BIT_EXT,
+    MESH_SHADER => MESH_SHADER_BIT_EXT,
+);
+
+impl Access {
+    pub const NONE: Access = Access::new(PipelineStageFlags::NONE, AccessTypeFlags::NONE);
+    pub const READ: Access = Access::new(PipelineStageFlags::ALL_COMMANDS_BIT, AccessTypeFlags::READ);
+    pub const WRITE: Access = Access::new(PipelineStageFlags::ALL_COMMANDS_BIT, AccessTypeFlags::WRITE);
+    pub const READ_WRITE: Access = Access::new(PipelineStageFlags::ALL_COMMANDS_BIT, AccessTypeFlags::READ_WRITE);
+
+    pub const fn new(stages: PipelineStageFlags, ty: AccessTypeFlags) -> Self {
+        Self { stages, ty }
+    }
+
+    pub fn is_write(&self) -> bool {
+        self.ty.contains(AccessTypeFlags::WRITE)
+    }
+}
+
+impl std::ops::BitOr for Access {
+    type Output = Access;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Access::new(self.stages | rhs.stages, self.ty | rhs.ty)
+    }
+}
+
+impl std::ops::BitOrAssign for Access {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct MemoryBarrierInfo {
+    pub src_access: Access,
+    pub dst_access: Access,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ImageMemoryBarrierInfo {
+    pub src_access: Access,
+    pub dst_access: Access,
+    pub src_layout: ImageLayout,
+    pub dst_layout: ImageLayout,
+    pub image_slice: ImageMipArraySlice,
+    pub image_id: ImageId,
+}
+
+// Barrier infos are passed to daxa as is.
+const _: () = assert!(mem::size_of::<Access>() == mem::size_of::<daxa_sys::daxa_Access>());
+const _: () = assert!(mem::align_of::<Access>() == mem::align_of::<daxa_sys::daxa_Access>());
+const _: () = assert!(mem::offset_of!(Access, stages) == mem::offset_of!(daxa_sys::daxa_Access, stages));
+const _: () = assert!(mem::offset_of!(Access, ty) == mem::offset_of!(daxa_sys::daxa_Access, type_));
+const _: () = assert!(mem::size_of::<MemoryBarrierInfo>() == mem::size_of::<daxa_sys::daxa_MemoryBarrierInfo>());
+const _: () = assert!(mem::align_of::<MemoryBarrierInfo>() == mem::align_of::<daxa_sys::daxa_MemoryBarrierInfo>());
+const _: () = assert!(mem::offset_of!(MemoryBarrierInfo, src_access) == mem::offset_of!(daxa_sys::daxa_MemoryBarrierInfo, src_access));
+const _: () = assert!(mem::offset_of!(MemoryBarrierInfo, dst_access) == mem::offset_of!(daxa_sys::daxa_MemoryBarrierInfo, dst_access));
+const _: () = assert!(mem::size_of::<ImageMemoryBarrierInfo>() == mem::size_of::<daxa_sys::daxa_ImageMemoryBarrierInfo>());
+const _: () = assert!(mem::align_of::<ImageMemoryBarrierInfo>() == mem::align_of::<daxa_sys::daxa_ImageMemoryBarrierInfo>());
+const _: () = assert!(mem::offset_of!(ImageMemoryBarrierInfo, src_access) == mem::offset_of!(daxa_sys::daxa_ImageMemoryBarrierInfo, src_access));
+const _: () = assert!(mem::offset_of!(ImageMemoryBarrierInfo, dst_access) == mem::offset_of!(daxa_sys::daxa_ImageMemoryBarrierInfo, dst_access));
+const _: () = assert!(mem::offset_of!(ImageMemoryBarrierInfo, src_layout) == mem::offset_of!(daxa_sys::daxa_ImageMemoryBarrierInfo, src_layout));
+const _: () = assert!(mem::offset_of!(ImageMemoryBarrierInfo, dst_layout) == mem::offset_of!(daxa_sys::daxa_ImageMemoryBarrierInfo, dst_layout));
+const _: () = assert!(mem::offset_of!(ImageMemoryBarrierInfo, image_slice) == mem::offset_of!(daxa_sys::daxa_ImageMemoryBarrierInfo, image_slice));
+const _: () = assert!(mem::offset_of!(ImageMemoryBarrierInfo, image_id) == mem::offset_of!(daxa_sys::daxa_ImageMemoryBarrierInfo, image_id));
+
 bitflags! {
     pub struct ImageViewType: i32 {
         const ONE_DIM = daxa_sys::VkImageViewType_VK_IMAGE_VIEW_TYPE_1D;
@@ -199,6 +320,14 @@ pub enum BorderColor {
     IntOpaqueWhite = daxa_sys::VkBorderColor_VK_BORDER_COLOR_INT_OPAQUE_WHITE,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Extent3D {
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+}
+
 pub enum Extent {
     OneDim(u32),
     TwoDim(u32, u32),
@@ -210,6 +339,21 @@ pub struct PresentInfo<'a> {
     pub swapchain: Swapchain,
 }
 
+pub struct TimelinePair<'a> {
+    pub semaphore: &'a TimelineSemaphore,
+    pub value: u64,
+}
+
+#[derive(Default)]
+pub struct CommandSubmitInfo<'a> {
+    pub wait_stages: PipelineStageFlags,
+    pub command_lists: &'a [ExecutableCommandList],
+    pub wait_binary_semaphores: &'a [BinarySemaphore],
+    pub signal_binary_semaphores: &'a [BinarySemaphore],
+    pub wait_timeline_semaphores: &'a [TimelinePair<'a>],
+    pub signal_timeline_semaphores: &'a [TimelinePair<'a>],
+}
+
 macro_rules! id {
     ($name:ident) => {
         paste::item! {
@@ -224,6 +368,88 @@ id!(Buffer);
 
 pub type BufferDeviceAddress = daxa_sys::daxa_BufferDeviceAddress;
 
+/// A buffer id that remembers the type of the elements stored in the buffer.
+pub struct TypedBuffer<T> {
+    id: BufferId,
+    _marker: PhantomData<T>,
+}
+
+impl<T> TypedBuffer<T> {
+    pub fn new(id: BufferId) -> Self {
+        Self {
+            id,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> BufferId {
+        self.id
+    }
+
+    /// The elements `range`, counted in elements of `T` from the start of the buffer.
+    pub fn slice(&self, range: std::ops::Range<usize>) -> TypedBufferSlice<T> {
+        TypedBufferSlice {
+            id: self.id,
+            offset: range.start,
+            count: range.end.saturating_sub(range.start),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn at(&self, index: usize) -> TypedBufferSlice<T> {
+        self.slice(index..index + 1)
+    }
+}
+
+impl<T> Clone for TypedBuffer<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for TypedBuffer<T> {}
+
+pub struct TypedBufferSlice<T> {
+    id: BufferId,
+    offset: usize,
+    count: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T> TypedBufferSlice<T> {
+    pub fn id(&self) -> BufferId {
+        self.id
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn byte_offset(&self) -> usize {
+        self.offset * mem::size_of::<T>()
+    }
+
+    pub fn byte_size(&self) -> usize {
+        self.count * mem::size_of::<T>()
+    }
+}
+
+impl<T> From<TypedBuffer<T>> for TypedBufferSlice<T> {
+    fn from(buffer: TypedBuffer<T>) -> Self {
+        buffer.at(0)
+    }
+}
+
+impl<T> Clone for TypedBufferSlice<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for TypedBufferSlice<T> {}
+
 #[repr(C)]
 pub struct BufferInfo {
     pub size: usize,
@@ -234,8 +460,9 @@ pub struct BufferInfo {
 #[repr(C)]
 pub struct ImageInfo {
     pub flags: ImageCreateFlags,
-    pub extent: Extent,
+    pub dimensions: u32,
     pub format: Format,
+    pub size: Extent3D,
     pub mip_level_count: u32,
     pub array_layer_count: u32,
     pub sample_count: u32,
@@ -244,6 +471,20 @@ pub struct ImageInfo {
     pub name: SmallString,
 }
 
+// Image infos are transmuted from and to daxa's when querying and creating images.
+const _: () = assert!(mem::size_of::<ImageInfo>() == mem::size_of::<daxa_sys::daxa_ImageInfo>());
+const _: () = assert!(mem::align_of::<ImageInfo>() == mem::align_of::<daxa_sys::daxa_ImageInfo>());
+const _: () = assert!(mem::offset_of!(ImageInfo, flags) == mem::offset_of!(daxa_sys::daxa_ImageInfo, flags));
+const _: () = assert!(mem::offset_of!(ImageInfo, dimensions) == mem::offset_of!(daxa_sys::daxa_ImageInfo, dimensions));
+const _: () = assert!(mem::offset_of!(ImageInfo, format) == mem::offset_of!(daxa_sys::daxa_ImageInfo, format));
+const _: () = assert!(mem::offset_of!(ImageInfo, size) == mem::offset_of!(daxa_sys::daxa_ImageInfo, size));
+const _: () = assert!(mem::offset_of!(ImageInfo, mip_level_count) == mem::offset_of!(daxa_sys::daxa_ImageInfo, mip_level_count));
+const _: () = assert!(mem::offset_of!(ImageInfo, array_layer_count) == mem::offset_of!(daxa_sys::daxa_ImageInfo, array_layer_count));
+const _: () = assert!(mem::offset_of!(ImageInfo, sample_count) == mem::offset_of!(daxa_sys::daxa_ImageInfo, sample_count));
+const _: () = assert!(mem::offset_of!(ImageInfo, usage) == mem::offset_of!(daxa_sys::daxa_ImageInfo, usage));
+const _: () = assert!(mem::offset_of!(ImageInfo, allocate_info) == mem::offset_of!(daxa_sys::daxa_ImageInfo, allocate_info));
+const _: () = assert!(mem::offset_of!(ImageInfo, name) == mem::offset_of!(daxa_sys::daxa_ImageInfo, name));
+
 #[repr(C)]
 pub struct ImageViewInfo {
     pub ty: ImageViewType,
@@ -307,14 +548,204 @@ handle!(ImageView, Id);
 handle!(Buffer, Id);
 handle!(Sampler, Id);
 handle!(RasterPipeline);
-handle!(ComputePipeline);
 handle!(Swapchain);
 handle!(BinarySemaphore);
 handle!(TimelineSemaphore);
-handle!(Event);
-handle!(TimelineQueryPool);
+
+impl Drop for RasterPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            daxa_sys::daxa_raster_pipeline_dec_refcnt(self.0);
+        }
+    }
+}
+
+// Pipelines are reference counted and immutable in daxa, so they can be shared between threads,
+// for example behind a [`crate::PipelineHandle`].
+unsafe impl Send for RasterPipeline {}
+unsafe impl Sync for RasterPipeline {}
+
+pub struct ComputePipeline {
+    pub(crate) handle: daxa_sys::daxa_ComputePipeline,
+    pub(crate) serial: u64,
+    pub(crate) workgroup_size: std::option::Option<[u32; 3]>,
+}
+
+impl ComputePipeline {
+    pub fn workgroup_size(&self) -> std::option::Option<[u32; 3]> {
+        self.workgroup_size
+    }
+}
+
+unsafe impl Send for ComputePipeline {}
+unsafe impl Sync for ComputePipeline {}
+
+impl Drop for ComputePipeline {
+    fn drop(&mut self) {
+        unsafe {
+            daxa_sys::daxa_compute_pipeline_dec_refcnt(self.handle);
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Default)]
+pub struct BinarySemaphoreInfo {
+    pub name: SmallString,
+}
+
+#[repr(C)]
+#[derive(Clone, Default)]
+pub struct TimelineSemaphoreInfo {
+    pub initial_value: u64,
+    pub name: SmallString,
+}
+
+impl Drop for BinarySemaphore {
+    fn drop(&mut self) {
+        unsafe {
+            daxa_sys::daxa_binary_semaphore_dec_refcnt(self.0);
+        }
+    }
+}
+
+impl Drop for TimelineSemaphore {
+    fn drop(&mut self) {
+        unsafe {
+            daxa_sys::daxa_timeline_semaphore_dec_refcnt(self.0);
+        }
+    }
+}
+
+impl TimelineSemaphore {
+    pub fn value(&self) -> std::result::Result<u64, Result> {
+        unsafe {
+            let mut value = 0;
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(
+                daxa_sys::daxa_timeline_semaphore_get_value(self.0, &mut value),
+            ) {
+                Result::Success => Ok(value),
+                error => Err(error),
+            }
+        }
+    }
+
+    /// Blocks until the semaphore reached `value` or `timeout` nanoseconds passed.
+    pub fn wait_for_value(&self, value: u64, timeout: u64) -> std::result::Result<(), Result> {
+        unsafe {
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(
+                daxa_sys::daxa_timeline_semaphore_wait_for_value(self.0, value, timeout),
+            ) {
+                Result::Success => Ok(()),
+                error => Err(error),
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Default)]
+pub struct EventInfo {
+    pub name: SmallString,
+}
+
+// Numbers objects that have no id in order of creation, so captures can tell them apart. Unlike
+// their addresses, serials are not reused once an object is destroyed.
+pub(crate) fn next_serial() -> u64 {
+    static NEXT_SERIAL: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+    NEXT_SERIAL.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+}
+
+pub struct Event {
+    pub(crate) handle: daxa_sys::daxa_Event,
+    pub(crate) serial: u64,
+    // Set by the device once a submission that leaves the event signaled went to the gpu.
+    pub(crate) signaled: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl Event {
+    pub(crate) fn new(handle: daxa_sys::daxa_Event) -> Self {
+        Self {
+            handle,
+            serial: next_serial(),
+            signaled: Default::default(),
+        }
+    }
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe {
+            daxa_sys::daxa_event_dec_refcnt(self.handle);
+        }
+    }
+}
+pub struct TimelineQueryPool {
+    pub(crate) handle: daxa_sys::daxa_TimelineQueryPool,
+    pub(crate) serial: u64,
+}
+
+impl Drop for TimelineQueryPool {
+    fn drop(&mut self) {
+        unsafe {
+            daxa_sys::daxa_timeline_query_pool_dec_refcnt(self.handle);
+        }
+    }
+}
+
+impl TimelineQueryPool {
+    /// Raw timestamp ticks of the queries `start_index..start_index + count`,
+    /// `None` for queries the gpu has not written yet. Never waits on the gpu.
+    pub fn query_ticks(
+        &self,
+        start_index: u32,
+        count: u32,
+    ) -> std::result::Result<Vec<std::option::Option<u64>>, Result> {
+        // daxa writes a (value, availability) pair per query.
+        let mut results = vec![0u64; count as usize * 2];
+        unsafe {
+            match mem::transmute::<daxa_sys::daxa_Result, Result>(
+                daxa_sys::daxa_timeline_query_pool_query_results(
+                    self.handle,
+                    start_index,
+                    count,
+                    results.as_mut_ptr(),
+                ),
+            ) {
+                Result::Success => {}
+                error => return Err(error),
+            }
+        }
+        Ok(results
+            .chunks_exact(2)
+            .map(|pair| (pair[1] != 0).then_some(pair[0]))
+            .collect())
+    }
+
+    /// Like [`Self::query_ticks`], converted to time using the device's timestamp period.
+    pub fn query_results(
+        &self,
+        device: &Device,
+        start_index: u32,
+        count: u32,
+    ) -> std::result::Result<Vec<std::option::Option<std::time::Duration>>, Result> {
+        let timestamp_period = device.properties().limits.timestampPeriod;
+        Ok(self
+            .query_ticks(start_index, count)?
+            .into_iter()
+            .map(|ticks| ticks.map(|ticks| ticks_to_duration(ticks, timestamp_period)))
+            .collect())
+    }
+}
+
+/// Converts gpu timestamp ticks to time, `timestamp_period` is in nanoseconds per tick.
+pub fn ticks_to_duration(ticks: u64, timestamp_period: f32) -> std::time::Duration {
+    std::time::Duration::from_nanos((ticks as f64 * timestamp_period as f64) as u64)
+}
 
 #[repr(i32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompareOp {
     Never = daxa_sys::VkCompareOp_VK_COMPARE_OP_NEVER,
     Less = daxa_sys::VkCompareOp_VK_COMPARE_OP_LESS,
@@ -326,6 +757,23 @@ pub enum CompareOp {
     Always = daxa_sys::VkCompareOp_VK_COMPARE_OP_ALWAYS,
 }
 
+#[repr(i32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AttachmentLoadOp {
+    Load = daxa_sys::VkAttachmentLoadOp_VK_ATTACHMENT_LOAD_OP_LOAD,
+    Clear = daxa_sys::VkAttachmentLoadOp_VK_ATTACHMENT_LOAD_OP_CLEAR,
+    DontCare = daxa_sys::VkAttachmentLoadOp_VK_ATTACHMENT_LOAD_OP_DONT_CARE,
+}
+
+#[repr(i32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AttachmentStoreOp {
+    Store = daxa_sys::VkAttachmentStoreOp_VK_ATTACHMENT_STORE_OP_STORE,
+    DontCare = daxa_sys::VkAttachmentStoreOp_VK_ATTACHMENT_STORE_OP_DONT_CARE,
+}
+
+pub type Rect2D = daxa_sys::VkRect2D;
+
 pub type DeviceSize = u64;
 
 bitflags! {
@@ -375,10 +823,10 @@ pub struct MemoryAllocateInfo {
 }
 
 #[repr(C)]
-#[derive(Clone)]
-pub struct AllocateInfo {
-    query_count: u32,
-    name: SmallString,
+#[derive(Clone, Default)]
+pub struct TimelineQueryPoolInfo {
+    pub query_count: u32,
+    pub name: SmallString,
 }
 
 #[repr(C)]
@@ -388,7 +836,7 @@ pub struct Option<T> {
     has_value: bool,
 }
 
-impl<T: 'static> From<std::option::Option<T>> for Option<T> {
+impl<T> From<std::option::Option<T>> for Option<T> {
     fn from(value: std::option::Option<T>) -> Self {
         let Some(data) = value else {
             return Option {
@@ -404,7 +852,32 @@ impl<T: 'static> From<std::option::Option<T>> for Option<T> {
     }
 }
 
-impl<T: 'static> Into<std::option::Option<T>> for Option<T> {
+impl<T> Option<T> {
+    pub fn as_ref(&self) -> std::option::Option<&T> {
+        if self.has_value {
+            Some(&self.data)
+        } else {
+            None
+        }
+    }
+}
+
+// Serialized like a `std::option::Option`.
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for Option<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.as_ref().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Option<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        std::option::Option::<T>::deserialize(deserializer).map(Into::into)
+    }
+}
+
+impl<T> Into<std::option::Option<T>> for Option<T> {
     fn into(self) -> std::option::Option<T> {
         let Option {
             has_value: true, ..
@@ -423,6 +896,18 @@ pub struct FixedString<const Capacity: usize> {
 }
 
 impl<const Capacity: usize> FixedString<Capacity> {
+    /// The longest string in bytes that fits, one byte is kept for the nul terminator.
+    pub const MAX_LEN: usize = Capacity - 1;
+
+    /// Cuts `string` at the last char boundary that fits instead of panicking like `From<&str>`.
+    pub fn truncated(string: &str) -> Self {
+        let mut len = string.len().min(Self::MAX_LEN);
+        while !string.is_char_boundary(len) {
+            len -= 1;
+        }
+        Self::from(&string[..len])
+    }
+
     pub unsafe fn from_ptr(ptr: *const os::raw::c_char, len: u8) -> Self {
         let mut data = [Default::default(); Capacity];
         unsafe { std::ptr::copy(ptr, &mut data as *mut _, len as _) };
@@ -435,6 +920,11 @@ impl<const Capacity: usize> FixedString<Capacity> {
         }
         Self::from_ptr(ptr, len)
     }
+    /// The string, or an empty one if it is not valid utf-8.
+    pub fn as_str(&self) -> &str {
+        let bytes = unsafe { std::slice::from_raw_parts(self.data.as_ptr().cast::<u8>(), self.len as usize) };
+        std::str::from_utf8(bytes).unwrap_or_default()
+    }
 }
 
 impl<'a, const Capacity: usize> From<&'a str> for FixedString<Capacity> {
@@ -458,6 +948,8 @@ impl<const Capacity: usize>  Default for FixedString<Capacity> {
 }
 
 #[repr(i32)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Format {
     Undefined = daxa_sys::VkFormat_VK_FORMAT_UNDEFINED,
     R4g4UnormPack8 = daxa_sys::VkFormat_VK_FORMAT_R4G4_UNORM_PACK8,
@@ -729,8 +1221,226 @@ pub enum Format {
     A8UnormKhr = daxa_sys::VkFormat_VK_FORMAT_A8_UNORM_KHR,
 }
 
+/// What kind of values the texels of a format hold, as seen by clears and shaders.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FormatClass {
+    /// Floating point, normalized, scaled and compressed formats.
+    Float,
+    Sint,
+    Uint,
+    /// Formats with a depth and/or a stencil aspect.
+    DepthStencil,
+}
+
+impl Format {
+    pub fn class(self) -> FormatClass {
+        match self {
+            Format::D16Unorm
+            | Format::X8D24UnormPack32
+            | Format::D32Sfloat
+            | Format::S8Uint
+            | Format::D16UnormS8Uint
+            | Format::D24UnormS8Uint
+            | Format::D32SfloatS8Uint => FormatClass::DepthStencil,
+            Format::R8Sint
+            | Format::R8g8Sint
+            | Format::R8g8b8Sint
+            | Format::B8g8r8Sint
+            | Format::R8g8b8a8Sint
+            | Format::B8g8r8a8Sint
+            | Format::A8b8g8r8SintPack32
+            | Format::A2r10g10b10SintPack32
+            | Format::A2b10g10r10SintPack32
+            | Format::R16Sint
+            | Format::R16g16Sint
+            | Format::R16g16b16Sint
+            | Format::R16g16b16a16Sint
+            | Format::R32Sint
+            | Format::R32g32Sint
+            | Format::R32g32b32Sint
+            | Format::R32g32b32a32Sint
+            | Format::R64Sint
+            | Format::R64g64Sint
+            | Format::R64g64b64Sint
+            | Format::R64g64b64a64Sint => FormatClass::Sint,
+            Format::R8Uint
+            | Format::R8g8Uint
+            | Format::R8g8b8Uint
+            | Format::B8g8r8Uint
+            | Format::R8g8b8a8Uint
+            | Format::B8g8r8a8Uint
+            | Format::A8b8g8r8UintPack32
+            | Format::A2r10g10b10UintPack32
+            | Format::A2b10g10r10UintPack32
+            | Format::R16Uint
+            | Format::R16g16Uint
+            | Format::R16g16b16Uint
+            | Format::R16g16b16a16Uint
+            | Format::R32Uint
+            | Format::R32g32Uint
+            | Format::R32g32b32Uint
+            | Format::R32g32b32a32Uint
+            | Format::R64Uint
+            | Format::R64g64Uint
+            | Format::R64g64b64Uint
+            | Format::R64g64b64a64Uint => FormatClass::Uint,
+            _ => FormatClass::Float,
+        }
+    }
+
+    /// Whether every Vulkan device can blit from and to the format with a linear filter.
+    ///
+    /// daxa has no format feature query, so this is the list of formats the Vulkan spec requires
+    /// to support linear filtering and blits on all devices. Other formats may work on some.
+    pub fn is_linear_blittable(self) -> bool {
+        matches!(
+            self,
+            Format::R5g6b5UnormPack16
+                | Format::A1r5g5b5UnormPack16
+                | Format::R8Unorm
+                | Format::R8g8Unorm
+                | Format::R8g8b8a8Unorm
+                | Format::R8g8b8a8Srgb
+                | Format::B8g8r8a8Unorm
+                | Format::B8g8r8a8Srgb
+                | Format::A8b8g8r8UnormPack32
+                | Format::A8b8g8r8SrgbPack32
+                | Format::A2b10g10r10UnormPack32
+                | Format::R16Sfloat
+                | Format::R16g16Sfloat
+                | Format::R16g16b16a16Sfloat
+                | Format::B10g11r11UfloatPack32
+        )
+    }
+
+    /// Block compressed formats can be sampled and copied, but not rendered or blitted to.
+    pub fn is_block_compressed(self) -> bool {
+        matches!(
+            self,
+                Format::Bc1RgbUnormBlock
+                | Format::Bc1RgbSrgbBlock
+                | Format::Bc1RgbaUnormBlock
+                | Format::Bc1RgbaSrgbBlock
+                | Format::Bc2UnormBlock
+                | Format::Bc2SrgbBlock
+                | Format::Bc3UnormBlock
+                | Format::Bc3SrgbBlock
+                | Format::Bc4UnormBlock
+                | Format::Bc4SnormBlock
+                | Format::Bc5UnormBlock
+                | Format::Bc5SnormBlock
+                | Format::Bc6hUfloatBlock
+                | Format::Bc6hSfloatBlock
+                | Format::Bc7UnormBlock
+                | Format::Bc7SrgbBlock
+                | Format::Etc2R8g8b8UnormBlock
+                | Format::Etc2R8g8b8SrgbBlock
+                | Format::Etc2R8g8b8a1UnormBlock
+                | Format::Etc2R8g8b8a1SrgbBlock
+                | Format::Etc2R8g8b8a8UnormBlock
+                | Format::Etc2R8g8b8a8SrgbBlock
+                | Format::EacR11UnormBlock
+                | Format::EacR11SnormBlock
+                | Format::EacR11g11UnormBlock
+                | Format::EacR11g11SnormBlock
+                | Format::Astc4x4UnormBlock
+                | Format::Astc4x4SrgbBlock
+                | Format::Astc5x4UnormBlock
+                | Format::Astc5x4SrgbBlock
+                | Format::Astc5x5UnormBlock
+                | Format::Astc5x5SrgbBlock
+                | Format::Astc6x5UnormBlock
+                | Format::Astc6x5SrgbBlock
+                | Format::Astc6x6UnormBlock
+                | Format::Astc6x6SrgbBlock
+                | Format::Astc8x5UnormBlock
+                | Format::Astc8x5SrgbBlock
+                | Format::Astc8x6UnormBlock
+                | Format::Astc8x6SrgbBlock
+                | Format::Astc8x8UnormBlock
+                | Format::Astc8x8SrgbBlock
+                | Format::Astc10x5UnormBlock
+                | Format::Astc10x5SrgbBlock
+                | Format::Astc10x6UnormBlock
+                | Format::Astc10x6SrgbBlock
+                | Format::Astc10x8UnormBlock
+                | Format::Astc10x8SrgbBlock
+                | Format::Astc10x10UnormBlock
+                | Format::Astc10x10SrgbBlock
+                | Format::Astc12x10UnormBlock
+                | Format::Astc12x10SrgbBlock
+                | Format::Astc12x12UnormBlock
+                | Format::Astc12x12SrgbBlock
+                | Format::Astc4x4SfloatBlock
+                | Format::Astc5x4SfloatBlock
+                | Format::Astc5x5SfloatBlock
+                | Format::Astc6x5SfloatBlock
+                | Format::Astc6x6SfloatBlock
+                | Format::Astc8x5SfloatBlock
+                | Format::Astc8x6SfloatBlock
+                | Format::Astc8x8SfloatBlock
+                | Format::Astc10x5SfloatBlock
+                | Format::Astc10x6SfloatBlock
+                | Format::Astc10x8SfloatBlock
+                | Format::Astc10x10SfloatBlock
+                | Format::Astc12x10SfloatBlock
+                | Format::Astc12x12SfloatBlock
+                | Format::Pvrtc12bppUnormBlockImg
+                | Format::Pvrtc14bppUnormBlockImg
+                | Format::Pvrtc22bppUnormBlockImg
+                | Format::Pvrtc24bppUnormBlockImg
+                | Format::Pvrtc12bppSrgbBlockImg
+                | Format::Pvrtc14bppSrgbBlockImg
+                | Format::Pvrtc22bppSrgbBlockImg
+                | Format::Pvrtc24bppSrgbBlockImg
+        )
+    }
+}
+
+/// The value an image or attachment is cleared to. It has to match the [`FormatClass`] of the
+/// image's format, see [`ClearValue::is_compatible`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ClearValue {
+    Float([f32; 4]),
+    Int([i32; 4]),
+    Uint([u32; 4]),
+    DepthStencil { depth: f32, stencil: u32 },
+}
+
+impl ClearValue {
+    pub fn is_compatible(&self, format: Format) -> bool {
+        matches!(
+            (self, format.class()),
+            (ClearValue::Float(_), FormatClass::Float)
+                | (ClearValue::Int(_), FormatClass::Sint)
+                | (ClearValue::Uint(_), FormatClass::Uint)
+                | (ClearValue::DepthStencil { .. }, FormatClass::DepthStencil)
+        )
+    }
+}
+
+impl From<ClearValue> for daxa_sys::VkClearValue {
+    fn from(value: ClearValue) -> Self {
+        match value {
+            ClearValue::Float(float32) => daxa_sys::VkClearValue {
+                color: daxa_sys::VkClearColorValue { float32 },
+            },
+            ClearValue::Int(int32) => daxa_sys::VkClearValue {
+                color: daxa_sys::VkClearColorValue { int32 },
+            },
+            ClearValue::Uint(uint32) => daxa_sys::VkClearValue {
+                color: daxa_sys::VkClearColorValue { uint32 },
+            },
+            ClearValue::DepthStencil { depth, stencil } => daxa_sys::VkClearValue {
+                depthStencil: daxa_sys::VkClearDepthStencilValue { depth, stencil },
+            },
+        }
+    }
+}
+
 pub const VK_UUID_SIZE: usize = 16;
 
+pub type DeviceProperties = daxa_sys::daxa_DeviceProperties;
 pub type VkPhysicalDeviceLimits = daxa_sys::VkPhysicalDeviceLimits;
 pub type VkPhysicalDeviceSparseProperties = daxa_sys::VkPhysicalDeviceSparseProperties;
 
@@ -745,3 +1455,50 @@ pub struct VkPhysicalDeviceProperties {
     limits: VkPhysicalDeviceLimits,
     sparse_properties: VkPhysicalDeviceSparseProperties,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn classifies_formats() {
+        assert_eq!(Format::R8g8b8a8Unorm.class(), FormatClass::Float);
+        assert_eq!(Format::R16g16b16a16Sfloat.class(), FormatClass::Float);
+        assert_eq!(Format::Bc7SrgbBlock.class(), FormatClass::Float);
+        assert_eq!(Format::R32Sint.class(), FormatClass::Sint);
+        assert_eq!(Format::A2b10g10r10UintPack32.class(), FormatClass::Uint);
+        assert_eq!(Format::D32Sfloat.class(), FormatClass::DepthStencil);
+        assert_eq!(Format::S8Uint.class(), FormatClass::DepthStencil);
+        assert!(Format::Bc7SrgbBlock.is_block_compressed());
+        assert!(!Format::R8g8b8a8Unorm.is_block_compressed());
+        assert!(Format::R16g16b16a16Sfloat.is_linear_blittable());
+        assert!(!Format::R32Sfloat.is_linear_blittable());
+        assert!(!Format::R8Uint.is_linear_blittable());
+    }
+
+    #[test]
+    fn converts_ticks_to_durations() {
+        assert_eq!(ticks_to_duration(0, 1.0), Duration::ZERO);
+        assert_eq!(ticks_to_duration(1000, 1.0), Duration::from_micros(1));
+        // Fractional nanoseconds are cut.
+        assert_eq!(ticks_to_duration(3, 2.5), Duration::from_nanos(7));
+        assert_eq!(ticks_to_duration(u32::MAX as u64, 10.0), Duration::from_nanos(u32::MAX as u64 * 10));
+    }
+
+    #[test]
+    fn matches_clear_values_to_format_classes() {
+        let depth = ClearValue::DepthStencil { depth: 1.0, stencil: 0 };
+        assert!(ClearValue::Float([0.0; 4]).is_compatible(Format::R8g8b8a8Srgb));
+        assert!(ClearValue::Int([0; 4]).is_compatible(Format::R16Sint));
+        assert!(ClearValue::Uint([0; 4]).is_compatible(Format::R32Uint));
+        assert!(depth.is_compatible(Format::D24UnormS8Uint));
+
+        assert!(!ClearValue::Float([0.0; 4]).is_compatible(Format::R32Uint));
+        assert!(!ClearValue::Uint([0; 4]).is_compatible(Format::R32Sint));
+        assert!(!ClearValue::Int([0; 4]).is_compatible(Format::R8Unorm));
+        assert!(!depth.is_compatible(Format::R32Sfloat));
+        assert!(!ClearValue::Float([0.0; 4]).is_compatible(Format::D32Sfloat));
+    }
+}